@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use crate::forge::DateTime;
+
+/// An append-only log of notification delivery attempts, one JSON object per line.
+pub struct AuditLog {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub attempted_at: DateTime,
+    pub channel: String,
+    pub subject: String,
+    pub recipients: Vec<String>,
+    /// URLs of the tasks reported as new.
+    pub notified: Vec<String>,
+    /// URLs of the tasks reported as still waiting for a response.
+    pub reminded: Vec<String>,
+    pub accepted: bool,
+    /// Response code of the server, none if the attempt failed before the server responded.
+    pub code: Option<String>,
+    /// Response message of the server, or the error of a failed attempt.
+    pub response: String,
+    pub message_id: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn mentions(&self, url: &str) -> bool {
+        self.notified.iter().chain(self.reminded.iter()).any(|task_url| task_url.contains(url))
+    }
+}
+
+impl AuditLog {
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = File::options()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // a single write per entry, so concurrent writers do not interleave lines
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Reads all entries, skipping lines that cannot be parsed, e.g. one cut short by a crash.
+    pub fn read(&self) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(Box::new(err)),
+        };
+        let mut entries = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line.as_str()) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("Skipping line {} of the audit log: {}", number + 1, e),
+            }
+        }
+        Ok(entries)
+    }
+}
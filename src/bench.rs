@@ -0,0 +1,91 @@
+use std::time::Instant;
+use core::time::Duration;
+use crate::forge::{FetchResult, NotificationHistory, Project, Task, TaskType};
+use crate::{check_tasks_against_persistence, Baseline, NotificationPolicy, ReminderCadence, StaleThresholds};
+
+const TASKS_PER_PROJECT: usize = 10;
+const RUNS: usize = 5;
+const SMALLEST: usize = 125;
+const LARGEST: usize = 8000;
+/// Tolerated growth of the time per task from the smallest to the largest number of projects, which would be a lot
+/// larger if a cycle was quadratic in the number of tasks.
+const MAX_SLOWDOWN: u32 = 4;
+
+fn synthetic_task(project: usize, number: usize, now: chrono::DateTime<chrono::Utc>) -> Task {
+    let created_at = now - chrono::Duration::hours((number % 72) as i64 + 1);
+    Task {
+        node_id: format!("T{}_{}", project, number),
+        observed_at: created_at,
+        task_type: match number % 3 {
+            0 => TaskType::Issue,
+            1 => TaskType::Pr,
+            _ => TaskType::Discussion,
+        },
+        id: number as i64,
+        title: format!("Task {}", number),
+        created_at,
+        url: format!("https://example.com/owner{}/repository{}/issues/{}", project % 100, project, number),
+        author: format!("author{}", number % 20),
+        updated_at: Some(created_at),
+        history: NotificationHistory { first_notified_at: Some(created_at), ..Default::default() },
+        missing_since: None,
+    }
+}
+
+fn synthetic_project(project: usize, tasks: Vec<Task>) -> Project {
+    Project {
+        node_id: format!("R{}", project),
+        name: format!("repository{}", project),
+        owner: format!("owner{}", project % 100),
+        url: format!("https://example.com/owner{}/repository{}", project % 100, project),
+        tasks,
+        activity_watermark: None,
+        first_seen_at: None,
+    }
+}
+
+/// Known state and fetch result of a cycle in which one task per project got closed and another one opened.
+fn synthetic_cycle(projects: usize, now: chrono::DateTime<chrono::Utc>) -> (Vec<Project>, Vec<Project>) {
+    let known = (0..projects)
+        .map(|project| synthetic_project(project, (0..TASKS_PER_PROJECT).map(|number| synthetic_task(project, number, now)).collect()))
+        .collect();
+    let fetched = (0..projects)
+        .map(|project| synthetic_project(project, (1..=TASKS_PER_PROJECT).map(|number| synthetic_task(project, number, now)).collect()))
+        .collect();
+    (known, fetched)
+}
+
+/// Fastest of a few cycles over the given number of projects, per task.
+fn time_per_task(projects: usize, policy: &NotificationPolicy, now: chrono::DateTime<chrono::Utc>) -> Duration {
+    let (known, fetched) = synthetic_cycle(projects, now);
+    let mut fastest = Duration::MAX;
+    for _ in 0..RUNS {
+        let fetched = FetchResult { projects: fetched.clone(), complete: true, unchanged: Vec::new() };
+        let started = Instant::now();
+        let result = check_tasks_against_persistence(fetched, now, policy, &known, &[]);
+        fastest = fastest.min(started.elapsed());
+        assert_eq!(result.notify.len(), projects, "expected a new task in each project");
+    }
+    let tasks = projects * TASKS_PER_PROJECT;
+    println!("{:>10} {:>10} {:>10.1}ms {:>12.0}ns", projects, tasks, fastest.as_secs_f64() * 1000.0, fastest.as_nanos() as f64 / tasks as f64);
+    fastest / tasks as u32
+}
+
+#[test]
+#[ignore = "benchmark, run with cargo test --release -- --ignored"]
+fn cycle_time_per_task_stays_flat() {
+    let policy = NotificationPolicy {
+        closed_retention: Duration::from_hours(24),
+        stale_after: StaleThresholds { issue: None, pr: None, discussion: None },
+        reminder_cadence: ReminderCadence::Interval(Duration::from_hours(24)),
+        max_reminders: None,
+        baseline: Some(Baseline { cutoff: None }),
+        full_scan_interval: Duration::from_hours(24),
+    };
+    let now = chrono::Utc::now();
+
+    println!("{:>10} {:>10} {:>12} {:>14}", "projects", "tasks", "cycle", "per task");
+    let smallest = time_per_task(SMALLEST, &policy, now);
+    let largest = time_per_task(LARGEST, &policy, now);
+    assert!(largest <= smallest * MAX_SLOWDOWN, "{:?} per task with {} projects, {:?} with {}", largest, LARGEST, smallest, SMALLEST);
+}
@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use ring::digest;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CassetteMode {
+    /// Perform requests as usual and save every request/response pair.
+    Record,
+    /// Serve previously recorded responses without any network access.
+    Replay,
+}
+
+/// A directory of recorded GraphQL request/response pairs, keyed on the operation name and the variables.
+pub struct Cassette {
+    pub mode: CassetteMode,
+    pub directory: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    operation: String,
+    variables: Value,
+    response: Value,
+}
+
+fn operation_name(request: &Value) -> String {
+    request.get("operationName")
+        .and_then(|name| name.as_str())
+        .unwrap_or("unnamed")
+        .to_string()
+}
+
+fn variables(request: &Value) -> Value {
+    request.get("variables").cloned().unwrap_or(Value::Null)
+}
+
+impl Cassette {
+    fn path(&self, operation: &str, variables: &Value) -> Result<PathBuf, Box<dyn Error>> {
+        // serde_json keeps object keys sorted, so equal variables always serialize the same way
+        let hash = digest::digest(&digest::SHA256, &serde_json::to_vec(variables)?);
+        let hex: String = hash.as_ref().iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+        Ok(self.directory.join(format!("{}-{}.json", operation, hex)))
+    }
+
+    pub fn replay(&self, request: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        let operation = operation_name(request);
+        let path = self.path(operation.as_str(), &variables(request))?;
+        let file = File::open(&path)
+            .map_err(|err| format!("no recording for {} at {}: {}", operation, path.display(), err))?;
+        let recording: Recording = serde_json::from_reader(BufReader::new(file))?;
+        Ok(serde_json::to_vec(&recording.response)?)
+    }
+
+    pub fn record(&self, request: &Value, response: &[u8]) -> Result<(), Box<dyn Error>> {
+        let recording = Recording {
+            operation: operation_name(request),
+            variables: variables(request),
+            response: serde_json::from_slice(response)?,
+        };
+        fs::create_dir_all(&self.directory)?;
+        let path = self.path(recording.operation.as_str(), &recording.variables)?;
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &recording)?;
+        Ok(())
+    }
+}
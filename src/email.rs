@@ -4,9 +4,8 @@ use std::error::Error as StdError;
 use lettre::{Address, SmtpTransport, Transport};
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::address::Envelope;
-use lettre::message::{Mailbox, MessageBuilder, SinglePart};
-use lettre::message::header::ContentType;
-use lettre::transport::smtp::client::{Tls, TlsParametersBuilder};
+use lettre::message::{Mailbox, MessageBuilder, MultiPart};
+use lettre::transport::smtp::client::{Certificate, Tls, TlsParametersBuilder};
 use lettre::transport::smtp::Error;
 use lettre::transport::smtp::response::Response;
 
@@ -16,25 +15,37 @@ pub struct EmailContext {
     to_address: Address,
 }
 
+/// An attempt to deliver a notification, with what is needed to audit it.
+pub struct Delivery {
+    pub message_id: Option<String>,
+    pub recipients: Vec<String>,
+    pub result: Result<Response, Error>,
+}
+
 pub enum TransportSecurity {
     None,
     StartTls,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_email_client(
     host: &str,
     port: u16,
     username: Option<String>,
     password: Option<String>,
     security: TransportSecurity,
+    ca_bundle: Option<&[u8]>,
     from_address: Address,
     to_address: Address,
 ) -> Result<EmailContext, Box<dyn StdError>> {
     let client_security = match security {
         TransportSecurity::None => Tls::None,
         TransportSecurity::StartTls => {
-            let tls_client_parameters =
-                TlsParametersBuilder::new(host.to_string()).build_rustls()?;
+            let mut tls_parameters_builder = TlsParametersBuilder::new(host.to_string());
+            if let Some(ca_bundle) = ca_bundle {
+                tls_parameters_builder = tls_parameters_builder.add_root_certificate(Certificate::from_pem(ca_bundle)?);
+            }
+            let tls_client_parameters = tls_parameters_builder.build_rustls()?;
             Tls::Required(tls_client_parameters)
         },
     };
@@ -67,22 +78,15 @@ pub fn send_email(
     context: &mut EmailContext,
     subject: &str,
     body: &str,
-) -> Result<Response, Error> {
+    html_body: &str,
+) -> Delivery {
     let envelope = Envelope::new(
         Some(context.from_address.to_owned()),
         vec![context.to_address.to_owned()],
     ).expect("failed to create envelope!");
 
-    let message = format!(r#"
-Tasks have been found in your projects, that you are not yet subscribed to.
-Check the following list.
-
-{}
-"#, body);
-
-    let body_part = SinglePart::builder()
-        .content_type(ContentType::TEXT_PLAIN)
-        .body(message.trim().to_string());
+    // clients that cannot display the HTML part fall back to the plain text one
+    let body_part = MultiPart::alternative_plain_html(body.to_string(), html_body.to_string());
 
     let message = MessageBuilder::new()
         .message_id(None)
@@ -90,8 +94,12 @@ Check the following list.
         .envelope(envelope)
         .from(Mailbox::new(None, context.from_address.to_owned()))
         .to(Mailbox::new(None, context.to_address.to_owned()))
-        .singlepart(body_part)
+        .multipart(body_part)
         .unwrap();
 
-    context.transport.send(&message)
+    Delivery {
+        message_id: message.headers().get_raw("Message-ID").map(str::to_string),
+        recipients: message.envelope().to().iter().map(Address::to_string).collect(),
+        result: context.transport.send(&message),
+    }
 }
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::io::Write;
+use serde::Serialize;
+use crate::forge::{DateTime, Project, Task, TaskType};
+
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "json-lines" => Some(ExportFormat::JsonLines),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Selects the known tasks to export, every filter that is set has to match.
+#[derive(Default)]
+pub struct ExportFilter {
+    pub owner: Option<String>,
+    /// Name or owner/name of the repository.
+    pub repository: Option<String>,
+    pub task_type: Option<TaskType>,
+    pub author: Option<String>,
+    /// Only tasks created before this time.
+    pub created_before: Option<DateTime>,
+    /// Only tasks created after this time.
+    pub created_after: Option<DateTime>,
+}
+
+impl ExportFilter {
+    fn matches(&self, project: &Project, task: &Task) -> bool {
+        self.owner.as_ref().is_none_or(|owner| owner.eq_ignore_ascii_case(&project.owner))
+            && self.repository.as_ref().is_none_or(|repository| {
+                repository.eq_ignore_ascii_case(&project.name)
+                    || repository.eq_ignore_ascii_case(&format!("{}/{}", project.owner, project.name))
+            })
+            && self.task_type.as_ref().is_none_or(|task_type| *task_type == task.task_type)
+            && self.author.as_ref().is_none_or(|author| author.eq_ignore_ascii_case(&task.author))
+            && self.created_before.is_none_or(|before| task.created_at < before)
+            && self.created_after.is_none_or(|after| task.created_at > after)
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExportRow<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<&'a str>,
+    pub owner: &'a str,
+    pub repository: &'a str,
+    pub task_type: &'a TaskType,
+    pub id: i64,
+    pub title: &'a str,
+    pub author: &'a str,
+    pub created_at: DateTime,
+    pub updated_at: Option<DateTime>,
+    pub url: &'a str,
+    pub first_notified_at: Option<DateTime>,
+    pub last_reminded_at: Option<DateTime>,
+    pub reminder_count: u32,
+    pub baselined: bool,
+    /// Since when the task has been missing from the fetched tasks, usually because it was closed.
+    pub missing_since: Option<DateTime>,
+}
+
+const COLUMNS: [&str; 15] = [
+    "identity", "owner", "repository", "task_type", "id", "title", "author", "created_at", "updated_at", "url",
+    "first_notified_at", "last_reminded_at", "reminder_count", "baselined", "missing_since",
+];
+
+pub fn export_rows<'a>(identity: Option<&'a str>, projects: &'a [Project], filter: &'a ExportFilter) -> impl Iterator<Item = ExportRow<'a>> + 'a {
+    projects.iter()
+        .flat_map(move |project| project.tasks.iter()
+            .filter(move |task| filter.matches(project, task))
+            .map(move |task| ExportRow {
+                identity,
+                owner: project.owner.as_str(),
+                repository: project.name.as_str(),
+                task_type: &task.task_type,
+                id: task.id,
+                title: task.title.as_str(),
+                author: task.author.as_str(),
+                created_at: task.created_at,
+                updated_at: task.updated_at,
+                url: task.url.as_str(),
+                first_notified_at: task.history.first_notified_at,
+                last_reminded_at: task.history.last_reminded_at,
+                reminder_count: task.history.reminder_count,
+                baselined: task.history.baselined,
+                missing_since: task.missing_since,
+            }))
+}
+
+impl ExportRow<'_> {
+    fn values(&self) -> Result<[String; 15], Box<dyn Error>> {
+        let datetime = |value: Option<DateTime>| value.map(|value| value.to_rfc3339()).unwrap_or_default();
+        let task_type = match serde_json::to_value(self.task_type)? {
+            serde_json::Value::String(name) => name,
+            other => other.to_string(),
+        };
+        Ok([
+            self.identity.unwrap_or_default().to_string(),
+            self.owner.to_string(),
+            self.repository.to_string(),
+            task_type,
+            self.id.to_string(),
+            self.title.to_string(),
+            self.author.to_string(),
+            self.created_at.to_rfc3339(),
+            datetime(self.updated_at),
+            self.url.to_string(),
+            datetime(self.first_notified_at),
+            datetime(self.last_reminded_at),
+            self.reminder_count.to_string(),
+            self.baselined.to_string(),
+            datetime(self.missing_since),
+        ])
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+pub fn write_export<'a>(out: &mut impl Write, format: &ExportFormat, rows: impl Iterator<Item = ExportRow<'a>>) -> Result<(), Box<dyn Error>> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+            for row in rows {
+                let values = row.values()?;
+                writeln!(out, "{}", values.iter().map(|value| csv_field(value)).collect::<Vec<_>>().join(","))?;
+            }
+        },
+        ExportFormat::JsonLines => {
+            for row in rows {
+                serde_json::to_writer(&mut *out, &row)?;
+                writeln!(out)?;
+            }
+        },
+        ExportFormat::Markdown => {
+            writeln!(out, "| {} |", COLUMNS.join(" | "))?;
+            writeln!(out, "|{}", "---|".repeat(COLUMNS.len()))?;
+            for row in rows {
+                let values = row.values()?;
+                writeln!(out, "| {} |", values.iter().map(|value| markdown_cell(value)).collect::<Vec<_>>().join(" | "))?;
+            }
+        },
+    }
+    Ok(())
+}
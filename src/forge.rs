@@ -0,0 +1,143 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use chrono::Utc;
+use futures::future::{ready, LocalBoxFuture};
+use futures::FutureExt;
+use serde::{Serialize, Deserialize};
+
+pub type DateTime = chrono::DateTime<Utc>;
+
+/// Identifies a project or task by its node ID, or by its URL if it was persisted before node IDs were tracked.
+pub fn node_key<'a>(node_id: &'a str, url: &'a str) -> &'a str {
+    match node_id.is_empty() {
+        true => url,
+        false => node_id,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Project {
+    /// GraphQL node ID, which stays the same when the repository is renamed or transferred.
+    /// Empty for projects persisted before node IDs were tracked.
+    #[serde(default)]
+    pub node_id: String,
+    pub name: String,
+    pub owner: String,
+    pub url: String,
+    pub tasks: Vec<Task>,
+    /// Latest update of any task of the repository at the time its tasks were last fetched, if the forge tracks activity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity_watermark: Option<DateTime>,
+    /// Time the project has first been seen, only tracked in baseline mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen_at: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Task {
+    /// GraphQL node ID, empty for tasks persisted before node IDs were tracked.
+    #[serde(default)]
+    pub node_id: String,
+    pub observed_at: DateTime,
+    pub task_type: TaskType,
+    pub id: i64,
+    pub title: String,
+    pub created_at: DateTime,
+    pub url: String,
+    pub author: String,
+    /// Time of the latest activity on the task, as of the last fetch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime>,
+    #[serde(flatten)]
+    pub history: NotificationHistory,
+    /// Time the task was first missing from a complete fetch, i.e. since when it has been closed or handled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_since: Option<DateTime>,
+}
+
+/// Notifications sent about a task so far.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct NotificationHistory {
+    /// Time the task has been reported as new, none for tasks persisted before the history was tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_notified_at: Option<DateTime>,
+    #[serde(default, alias = "reminded_at", skip_serializing_if = "Option::is_none")]
+    pub last_reminded_at: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reminder_count: u32,
+    /// Whether the task has been recorded silently in baseline mode, in which case it is never reported.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub baselined: bool,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskType {
+    Issue,
+    Pr,
+    Discussion,
+}
+
+pub struct FetchResult {
+    pub projects: Vec<Project>,
+    /// Whether all open tasks have been fetched, so known tasks missing from the result can be considered closed.
+    pub complete: bool,
+    /// Node IDs of known projects that have not changed since their tasks were last fetched and were skipped.
+    pub unchanged: Vec<String>,
+}
+
+/// A source of projects with open tasks the user is not yet subscribed to.
+pub trait Forge {
+    /// Fetches the projects with unsubscribed tasks, complete results include projects without any. Forges may limit
+    /// the result to tasks created after `since`, the start of the last successful cycle, in which case the result is
+    /// not complete. The `known` projects allow forges to skip projects without any activity since their last fetch.
+    fn fetch_projects<'a>(&'a self, since: Option<DateTime>, known: &'a [Project]) -> LocalBoxFuture<'a, Result<FetchResult, Box<dyn Error>>>;
+
+    /// Fetches the projects of the tasks with the given node IDs, as reported by webhook deliveries.
+    /// Node IDs that do not belong to this forge are ignored.
+    fn fetch_projects_by_node_ids(&self, node_ids: &[String]) -> LocalBoxFuture<'_, Result<Vec<Project>, Box<dyn Error>>> {
+        let _ = node_ids;
+        ready(Ok(Vec::new())).boxed_local()
+    }
+}
+
+/// A forge replaying a script instead of talking to a remote API, so cycles can be run offline.
+/// Every fetch yields the next step of the script, the last step is repeated once the script is exhausted.
+pub struct InMemoryForge {
+    steps: Vec<Vec<Project>>,
+    position: Cell<usize>,
+}
+
+impl InMemoryForge {
+    pub fn new(steps: Vec<Vec<Project>>) -> Self {
+        InMemoryForge { steps, position: Cell::new(0) }
+    }
+
+    /// Reads the script from a JSON file containing a list of steps, each being a list of projects.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let steps = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(Self::new(steps))
+    }
+}
+
+impl Forge for InMemoryForge {
+    fn fetch_projects<'a>(&'a self, _since: Option<DateTime>, _known: &'a [Project]) -> LocalBoxFuture<'a, Result<FetchResult, Box<dyn Error>>> {
+        let position = self.position.get();
+        let projects = match self.steps.get(position).or(self.steps.last()) {
+            Some(step) => step.clone(),
+            None => Vec::new(),
+        };
+        self.position.set(position + 1);
+        ready(Ok(FetchResult { projects, complete: true, unchanged: Vec::new() })).boxed_local()
+    }
+}
@@ -0,0 +1,202 @@
+use std::error::Error;
+use std::cmp::Reverse;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use reqwest::Url;
+use crate::error::QueryError;
+use crate::forge::{DateTime, FetchResult, Forge, NotificationHistory, Project, Task, TaskType};
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+
+const PAGE_SIZE: usize = 50;
+/// Upper bound of subscription lookups in flight per task listing.
+const SUBSCRIPTION_CONCURRENCY: usize = 8;
+/// Upper bound of repositories fetched at once.
+const PROJECT_CONCURRENCY: usize = 4;
+
+pub struct GiteaClientContext {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub username: String,
+    pub access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repo {
+    id: i64,
+    name: String,
+    html_url: String,
+    owner: Owner,
+    #[serde(default)]
+    archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subject {
+    id: i64,
+    number: i64,
+    title: String,
+    created_at: DateTime,
+    updated_at: DateTime,
+    html_url: String,
+    user: Option<Owner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchInfo {
+    subscribed: bool,
+}
+
+fn api_url(context: &GiteaClientContext, segments: &[&str]) -> Result<Url, Box<dyn Error>> {
+    let mut url = Url::parse(context.base_url.as_str())?;
+    url.path_segments_mut()
+        .map_err(|_| "invalid Gitea base URL")?
+        .pop_if_empty()
+        .extend(["api", "v1"])
+        .extend(segments);
+    Ok(url)
+}
+
+async fn get_page<Res>(context: &GiteaClientContext, url: Url) -> Result<(Res, Option<usize>), Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let response = context.client.get(url)
+        .header("Authorization", format!("token {}", context.access_token))
+        .header("User-Agent", "ProjectMonitor")
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Box::new(QueryError::HttpError(status.as_u16())));
+    }
+    let total_count = response.headers()
+        .get("X-Total-Count")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    let bytes = response.bytes().await?;
+    Ok((serde_json::from_slice(&bytes)?, total_count))
+}
+
+async fn get<Res>(context: &GiteaClientContext, url: Url) -> Result<Res, Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let (result, _) = get_page(context, url).await?;
+    Ok(result)
+}
+
+async fn get_all<Res>(context: &GiteaClientContext, base_url: Url) -> Result<Vec<Res>, Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let mut output: Vec<Res> = Vec::new();
+    let mut page = 1;
+    loop {
+        let mut url = base_url.clone();
+        url.query_pairs_mut()
+            .append_pair("limit", PAGE_SIZE.to_string().as_str())
+            .append_pair("page", page.to_string().as_str());
+        // the server caps the page size at its own maximum, so only the total count tells whether pages remain
+        let (values, total_count) = get_page::<Vec<Res>>(context, url).await?;
+        let received = values.len();
+        output.extend(values);
+        let has_next_page = match total_count {
+            Some(total_count) => received > 0 && output.len() < total_count,
+            None => received > 0,
+        };
+
+        if !has_next_page {
+            break;
+        }
+        page += 1;
+    }
+    Ok(output)
+}
+
+async fn is_subscribed(context: &GiteaClientContext, repo: &Repo, number: i64) -> Result<(i64, bool), Box<dyn Error>> {
+    let number_segment = number.to_string();
+    let url = api_url(context, &["repos", repo.owner.login.as_str(), repo.name.as_str(), "issues", number_segment.as_str(), "subscriptions", "check"])?;
+    let watch_info: WatchInfo = get(context, url).await?;
+    Ok((number, watch_info.subscribed))
+}
+
+async fn fetch_tasks(context: &GiteaClientContext, repo: &Repo, collection: &str, task_type: TaskType) -> Result<Vec<Task>, Box<dyn Error>> {
+    let mut url = api_url(context, &["repos", repo.owner.login.as_str(), repo.name.as_str(), collection])?;
+    url.query_pairs_mut().append_pair("state", "open");
+    if collection == "issues" {
+        // the issue listing includes pull requests unless told otherwise
+        url.query_pairs_mut().append_pair("type", "issues");
+    }
+    let subjects: Vec<Subject> = get_all(context, url).await?;
+    let subjects: Vec<Subject> = subjects.into_iter()
+        .filter(|subject| subject.user.as_ref().map(|user| user.login.as_str()) != Some(context.username.as_str()))
+        .collect();
+
+    let subscribed: HashSet<i64> = stream::iter(subjects.iter())
+        .map(|subject| is_subscribed(context, repo, subject.number))
+        .buffer_unordered(SUBSCRIPTION_CONCURRENCY)
+        .try_filter_map(|(number, subscribed)| async move { Ok(subscribed.then_some(number)) })
+        .try_collect()
+        .await?;
+
+    let tasks = subjects.into_iter()
+        .filter(|subject| !subscribed.contains(&subject.number))
+        .map(|subject| Task {
+            node_id: match task_type {
+                TaskType::Pr => format!("gid://gitea/PullRequest/{}", subject.id),
+                _ => format!("gid://gitea/Issue/{}", subject.id),
+            },
+            observed_at: Utc::now(),
+            task_type: task_type.clone(),
+            id: subject.number,
+            title: subject.title,
+            created_at: subject.created_at,
+            updated_at: Some(subject.updated_at),
+            history: NotificationHistory::default(),
+            missing_since: None,
+            url: subject.html_url,
+            author: subject.user.map(|user| user.login).unwrap_or("<deleted user>".to_string()),
+        })
+        .collect();
+    Ok(tasks)
+}
+
+async fn fetch_project(context: &GiteaClientContext, repo: &Repo) -> Result<Project, Box<dyn Error>> {
+    let mut tasks: Vec<Task> = Vec::new();
+    tasks.extend(fetch_tasks(context, repo, "issues", TaskType::Issue).await?);
+    tasks.extend(fetch_tasks(context, repo, "pulls", TaskType::Pr).await?);
+    tasks.sort_by_key(|task| Reverse(task.created_at));
+
+    Ok(Project {
+        node_id: format!("gid://gitea/Repository/{}", repo.id),
+        name: repo.name.clone(),
+        owner: repo.owner.login.clone(),
+        url: repo.html_url.clone(),
+        tasks,
+        activity_watermark: None,
+        first_seen_at: None,
+    })
+}
+
+async fn fetch_all_projects(context: &GiteaClientContext) -> Result<Vec<Project>, Box<dyn Error>> {
+    let repos: Vec<Repo> = get_all(context, api_url(context, &["user", "repos"])?).await?;
+    stream::iter(repos.iter().filter(|repo| !repo.archived))
+        .map(|repo| fetch_project(context, repo))
+        .buffer_unordered(PROJECT_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+impl Forge for GiteaClientContext {
+    fn fetch_projects<'a>(&'a self, _since: Option<DateTime>, _known: &'a [Project]) -> LocalBoxFuture<'a, Result<FetchResult, Box<dyn Error>>> {
+        async move {
+            Ok(FetchResult { projects: fetch_all_projects(self).await?, complete: true, unchanged: Vec::new() })
+        }.boxed_local()
+    }
+}
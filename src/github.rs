@@ -1,5 +1,5 @@
 use std::error::Error;
-use chrono::Utc;
+use chrono::{SecondsFormat, Utc};
 use std::cmp::Reverse;
 use serde::{Serialize, Deserialize};
 use serde::de::DeserializeOwned;
@@ -7,8 +7,20 @@ use std::fmt::Debug;
 use graphql_client::{GraphQLQuery, Response};
 use futures::future::join_all;
 use tokio::try_join;
+use crate::cassette::{Cassette, CassetteMode};
 use crate::error::QueryError;
-
+use crate::forge::{FetchResult, Forge, NotificationHistory, Project, Task, TaskType};
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use core::time::Duration;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[allow(clippy::upper_case_acronyms)]
 type URI = String;
 type DateTime = chrono::DateTime<Utc>;
 
@@ -44,51 +56,158 @@ pub struct OrganizationReposQuery;
 )]
 pub struct RepoQuery;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct Project {
-    pub name: String,
-    pub owner: String,
-    pub url: URI,
-    pub tasks: Vec<Task>,
-}
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/github.graphql",
+    query_path = "src/query.graphql",
+    response_derives = "Debug",
+    variables_derives = "Clone",
+)]
+pub struct SearchQuery;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct Task {
-    pub observed_at: chrono::DateTime<Utc>,
-    pub task_type: TaskType,
-    pub id: i64,
-    pub title: String,
-    pub created_at: DateTime,
-    pub url: URI,
-    pub author: String,
-}
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/github.graphql",
+    query_path = "src/query.graphql",
+    response_derives = "Debug",
+)]
+pub struct NodesQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/github.graphql",
+    query_path = "src/query.graphql",
+    response_derives = "Debug",
+)]
+pub struct RepoActivityQuery;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum TaskType {
-    Issue,
-    Pr,
-    Discussion,
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/github.graphql",
+    query_path = "src/query.graphql",
+    response_derives = "Debug",
+)]
+pub struct TeamMembersQuery;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FetchStrategy {
+    /// Enumerate all repositories and query each of them for open tasks.
+    FullScan,
+    /// Use the search API to only find tasks created since the last successful cycle.
+    Search,
 }
 
 pub struct GithubClientContext {
     pub client: reqwest::Client,
     pub username: String,
     pub access_token: String,
+    pub fetch_strategy: FetchStrategy,
+    pub cassette: Option<Cassette>,
+    pub repository_cache: Option<RepositoryCache>,
+    /// Skip repositories without activity since their tasks were last fetched.
+    pub skip_unchanged: bool,
+    pub maintainers: Maintainers,
+    /// Reaction the viewer or a maintainer leaves on a task to mark it as seen.
+    pub acknowledgement: Option<Reaction>,
 }
 
-impl PartialEq for repo_query::SubscriptionState {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (&repo_query::SubscriptionState::SUBSCRIBED, &repo_query::SubscriptionState::SUBSCRIBED) => true,
-            (&repo_query::SubscriptionState::UNSUBSCRIBED, &repo_query::SubscriptionState::UNSUBSCRIBED) => true,
-            (&repo_query::SubscriptionState::IGNORED, &repo_query::SubscriptionState::IGNORED) => true,
-            _ => false,
+/// Colleagues sharing the repositories, tasks any of them engaged with are not reported.
+pub struct Maintainers {
+    pub logins: Vec<String>,
+    /// Team whose members are maintainers as well, in the form organization/team-slug.
+    pub team: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reaction {
+    ThumbsUp,
+    ThumbsDown,
+    Laugh,
+    Hooray,
+    Confused,
+    Heart,
+    Rocket,
+    Eyes,
+}
+
+macro_rules! reaction_content {
+    ($module:ident, $reaction:expr) => {
+        match $reaction {
+            Reaction::ThumbsUp => $module::ReactionContent::THUMBS_UP,
+            Reaction::ThumbsDown => $module::ReactionContent::THUMBS_DOWN,
+            Reaction::Laugh => $module::ReactionContent::LAUGH,
+            Reaction::Hooray => $module::ReactionContent::HOORAY,
+            Reaction::Confused => $module::ReactionContent::CONFUSED,
+            Reaction::Heart => $module::ReactionContent::HEART,
+            Reaction::Rocket => $module::ReactionContent::ROCKET,
+            Reaction::Eyes => $module::ReactionContent::EYES,
         }
+    };
+}
+
+// without an acknowledgement reaction configured, the queries skip fetching reactions altogether
+fn acknowledgement_limit(context: &GithubClientContext) -> i64 {
+    match context.acknowledgement {
+        Some(_) => 100,
+        None => 0,
     }
+}
 
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+/// Number of comments and reviews to look for maintainers in, none if there are no maintainers to look for.
+fn engagement_limit(coverage: &Coverage) -> i64 {
+    match coverage.maintainers.is_empty() {
+        true => 0,
+        false => 100,
+    }
+}
+
+/// Who counts as having handled a task, resolved at the start of every fetch.
+struct Coverage {
+    maintainers: HashSet<String>,
+    /// Users whose acknowledgement reaction marks a task as handled, the viewer and the maintainers.
+    acknowledging: HashSet<String>,
+}
+
+/// Persists the discovered repositories, so discovery only runs once per refresh interval.
+pub struct RepositoryCache {
+    pub path: PathBuf,
+    pub refresh_interval: Duration,
+    /// Set to discard the cached repositories during the next cycle.
+    pub force_refresh: Arc<AtomicBool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedRepos {
+    discovered_at: DateTime,
+    repos: Vec<Repo>,
+}
+
+impl RepositoryCache {
+    fn read(&self) -> Option<CachedRepos> {
+        let file = File::open(&self.path).ok()?;
+        match serde_json::from_reader(BufReader::new(file)) {
+            Ok(cached) => Some(cached),
+            Err(e) => {
+                eprintln!("Failed to parse repository cache: {}", e);
+                None
+            },
+        }
+    }
+
+    fn write(&self, cached: &CachedRepos) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&self.path)?), cached)?;
+        Ok(())
+    }
+}
+
+impl PartialEq for repo_query::SubscriptionState {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (repo_query::SubscriptionState::SUBSCRIBED, repo_query::SubscriptionState::SUBSCRIBED)
+                | (repo_query::SubscriptionState::UNSUBSCRIBED, repo_query::SubscriptionState::UNSUBSCRIBED)
+                | (repo_query::SubscriptionState::IGNORED, repo_query::SubscriptionState::IGNORED)
+        )
     }
 }
 
@@ -100,18 +219,28 @@ async fn run_query<Req, Res>(
         Req: Serialize,
         Res: DeserializeOwned,
         Res: Debug {
-    let req = context.client.post("https://api.github.com/graphql")
-        .json(&request_body)
-        .basic_auth(context.username.as_str(), Some(context.access_token.as_str()))
-        .header("User-Agent", "ProjectMonitor");
-    let response = req
-        .send()
-        .await?;
-    let status = response.status();
-    if !status.is_success() {
-        return Err(Box::new(QueryError::HttpError(status.as_u16())));
-    }
-    let bytes = response.bytes().await?;
+    let request = serde_json::to_value(&request_body)?;
+    let bytes = match &context.cassette {
+        Some(cassette) if cassette.mode == CassetteMode::Replay => cassette.replay(&request)?,
+        _ => {
+            let req = context.client.post("https://api.github.com/graphql")
+                .json(&request)
+                .basic_auth(context.username.as_str(), Some(context.access_token.as_str()))
+                .header("User-Agent", "ProjectMonitor");
+            let response = req
+                .send()
+                .await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(Box::new(QueryError::HttpError(status.as_u16())));
+            }
+            let bytes = response.bytes().await?.to_vec();
+            if let Some(cassette) = &context.cassette {
+                cassette.record(&request, &bytes)?;
+            }
+            bytes
+        },
+    };
     let body: Response<Res> = serde_json::from_slice(&bytes)?;
 
     match body.data {
@@ -122,8 +251,11 @@ async fn run_query<Req, Res>(
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Repo {
+    /// Empty for repositories cached before node IDs were tracked.
+    #[serde(default)]
+    id: String,
     owner: String,
     name: String,
 }
@@ -132,7 +264,7 @@ async fn fetch_viewer_repos(context: &GithubClientContext) -> Result<Vec<Repo>,
     let mut output: Vec<Repo> = Vec::new();
     let mut cursor: Option<String> = None;
     loop {
-        let variables = viewer_repos_query::Variables { cursor: cursor.as_ref().map(|a| a.clone()) };
+        let variables = viewer_repos_query::Variables { cursor: cursor.clone() };
         let result = run_query::<_, viewer_repos_query::ResponseData>(context, ViewerReposQuery::build_query(variables)).await?;
         let values = result.viewer.repositories.edges
             .into_iter()
@@ -140,7 +272,7 @@ async fn fetch_viewer_repos(context: &GithubClientContext) -> Result<Vec<Repo>,
             .flatten()
             .flat_map(|edge| edge.node)
             .filter(|repo| !repo.is_archived)
-            .map(|repo| Repo { owner: repo.owner.login, name: repo.name });
+            .map(|repo| Repo { id: repo.id, owner: repo.owner.login, name: repo.name });
 
         output.extend(values);
 
@@ -157,7 +289,7 @@ async fn fetch_viewer_organizations(context: &GithubClientContext) -> Result<Vec
     let mut output: Vec<String> = Vec::new();
     let mut cursor: Option<String> = None;
     loop {
-        let variables = viewer_organizations_query::Variables { cursor: cursor.as_ref().map(|a| a.clone()) };
+        let variables = viewer_organizations_query::Variables { cursor: cursor.clone() };
         let result = run_query::<_, viewer_organizations_query::ResponseData>(context, ViewerOrganizationsQuery::build_query(variables)).await?;
         let values = result.viewer.organizations.edges
             .into_iter()
@@ -182,7 +314,7 @@ async fn fetch_orga_repos(context: &GithubClientContext, login: &str) -> Result<
     let mut output: Vec<Repo> = Vec::new();
     let mut cursor: Option<String> = None;
     loop {
-        let variables = organization_repos_query::Variables { login: login.to_string(), cursor: cursor.as_ref().map(|a| a.clone()) };
+        let variables = organization_repos_query::Variables { login: login.to_string(), cursor: cursor.clone() };
         let result = run_query::<_, organization_repos_query::ResponseData>(context, OrganizationReposQuery::build_query(variables)).await?;
         let orga_repos = result.organization.ok_or("no organization")?.repositories;
         let values = orga_repos.edges
@@ -191,7 +323,7 @@ async fn fetch_orga_repos(context: &GithubClientContext, login: &str) -> Result<
             .flatten()
             .flat_map(|edge| edge.node)
             .filter(|repo| !repo.is_archived)
-            .map(|repo| Repo { owner: repo.owner.login, name: repo.name });
+            .map(|repo| Repo { id: repo.id, owner: repo.owner.login, name: repo.name });
 
         output.extend(values);
 
@@ -205,7 +337,7 @@ async fn fetch_orga_repos(context: &GithubClientContext, login: &str) -> Result<
 }
 
 async fn fetch_all_orga_repos(context: &GithubClientContext) -> Result<Vec<Repo>, Box<dyn Error>> {
-    let orgas = &fetch_viewer_organizations(&context).await?;
+    let orgas = &fetch_viewer_organizations(context).await?;
     let mut futures = Vec::new();
     for orga in orgas {
         futures.push(fetch_orga_repos(context, orga.as_str()));
@@ -219,10 +351,10 @@ async fn fetch_all_orga_repos(context: &GithubClientContext) -> Result<Vec<Repo>
 
 async fn fetch_all_repos(context: &GithubClientContext) -> Result<Vec<Repo>, Box<dyn Error>> {
     let (viewer_repos, orga_repos) =
-        try_join!(fetch_viewer_repos(&context), fetch_all_orga_repos(&context))?;
+        try_join!(fetch_viewer_repos(context), fetch_all_orga_repos(context))?;
 
     let repos: Vec<Repo> = viewer_repos.into_iter()
-         .chain(orga_repos.into_iter())
+         .chain(orga_repos)
          .collect();
 
     Ok(repos)
@@ -254,72 +386,440 @@ impl Authored for repo_query::RepoQueryRepositoryDiscussionsEdgesNode {
     }
 }
 
+/// Tasks maintainers can engage with by commenting, reviewing, being assigned or reacting.
+trait Engaged {
+    fn participants(&self) -> Vec<&str>;
+
+    /// Users who left the acknowledgement reaction, the query only fetches reactions of that kind.
+    fn reactors(&self) -> Vec<&str>;
+
+    fn is_covered(&self, coverage: &Coverage) -> bool {
+        self.participants().into_iter().any(|login| coverage.maintainers.contains(login))
+            || self.reactors().into_iter().any(|login| coverage.acknowledging.contains(login))
+    }
+}
+
+macro_rules! impl_engaged {
+    ($type:ty, assigned: [$($assigned:ident),*], authored: [$($authored:ident),*], optionally_authored: [$($optionally_authored:ident),*]) => {
+        impl Engaged for $type {
+            fn participants(&self) -> Vec<&str> {
+                let mut logins: Vec<&str> = Vec::new();
+                $(logins.extend(self.$assigned.nodes.iter().flatten().flatten().map(|user| user.login.as_str()));)*
+                $(logins.extend(self.$authored.nodes.iter().flatten().flatten().flat_map(|node| node.author.as_ref()).map(|author| author.login.as_str()));)*
+                $(logins.extend(self.$optionally_authored.iter().flat_map(|connection| connection.nodes.iter()).flatten().flatten().flat_map(|node| node.author.as_ref()).map(|author| author.login.as_str()));)*
+                logins
+            }
+
+            fn reactors(&self) -> Vec<&str> {
+                self.reactions.nodes.iter().flatten().flatten()
+                    .flat_map(|reaction| reaction.user.as_ref())
+                    .map(|user| user.login.as_str())
+                    .collect()
+            }
+        }
+    };
+}
+
+impl_engaged!(repo_query::RepoQueryRepositoryIssuesEdgesNode, assigned: [assignees], authored: [comments], optionally_authored: []);
+impl_engaged!(repo_query::RepoQueryRepositoryPullRequestsEdgesNode, assigned: [assignees], authored: [comments], optionally_authored: [reviews]);
+impl_engaged!(repo_query::RepoQueryRepositoryDiscussionsEdgesNode, assigned: [], authored: [comments], optionally_authored: []);
+impl_engaged!(nodes_query::NodesQueryNodesOnIssue, assigned: [assignees], authored: [comments], optionally_authored: []);
+impl_engaged!(nodes_query::NodesQueryNodesOnPullRequest, assigned: [assignees], authored: [comments], optionally_authored: [reviews]);
+impl_engaged!(nodes_query::NodesQueryNodesOnDiscussion, assigned: [], authored: [comments], optionally_authored: []);
+
 macro_rules! fetch_tasks {
-    ($context:expr, $repo:expr, $field:ident, $type:ident) => {
+    ($context:expr, $coverage:expr, $repo:expr, $field:ident, $type:ident) => {
         $repo.$field.edges
             .into_iter()
             .flatten()
             .flatten()
             .flat_map(|edge| edge.node)
             .filter(|subject| subject.viewer_subscription.as_ref() != Some(&repo_query::SubscriptionState::SUBSCRIBED))
-            .map(|subject| Task { observed_at: Utc::now(), task_type: TaskType::$type, id: subject.number, author: subject.get_author_name_or_default(), url: subject.url, title: subject.title, created_at: subject.created_at })
+            .filter(|subject| !subject.is_covered($coverage))
+            .map(|subject| Task { author: subject.get_author_name_or_default(), node_id: subject.id, observed_at: Utc::now(), task_type: TaskType::$type, id: subject.number, url: subject.url, title: subject.title, created_at: subject.created_at, updated_at: Some(subject.updated_at), history: NotificationHistory::default(), missing_since: None })
             .filter(|subject| subject.author != $context.username)
     };
 }
 
-async fn fetch_project(context: &GithubClientContext, owner: &str, name: &str) -> Result<Project, Box<dyn Error>> {
+async fn fetch_project(context: &GithubClientContext, coverage: &Coverage, owner: &str, name: &str) -> Result<Project, Box<dyn Error>> {
     let mut tasks: Vec<Task> = Vec::new();
     let mut issue_cursor: Option<String> = None;
     let mut pull_request_cursor: Option<String> = None;
     let mut discussion_cursor: Option<String> = None;
+    let mut issues_pending = true;
+    let mut pull_requests_pending = true;
+    let mut discussions_pending = true;
     let mut repo;
 
     loop {
         let variables = repo_query::Variables {
             owner: owner.to_string(),
             name: name.to_string(),
-            issue_cursor: issue_cursor.as_ref().map(|a| a.clone()),
-            pull_request_cursor: pull_request_cursor.as_ref().map(|a| a.clone()),
-            discussion_cursor: discussion_cursor.as_ref().map(|a| a.clone()),
+            issue_cursor: issue_cursor.clone(),
+            pull_request_cursor: pull_request_cursor.clone(),
+            discussion_cursor: discussion_cursor.clone(),
+            acknowledgement: context.acknowledgement.map(|reaction| reaction_content!(repo_query, reaction)),
+            acknowledgement_limit: acknowledgement_limit(context),
+            engagement_limit: engagement_limit(coverage),
         };
         let result = run_query::<_, repo_query::ResponseData>(context, RepoQuery::build_query(variables)).await?;
         repo = result.repository.ok_or("no repository")?;
 
-        tasks.extend(fetch_tasks!(context, repo, issues, Issue));
-        tasks.extend(fetch_tasks!(context, repo, pull_requests, Pr));
-        tasks.extend(fetch_tasks!(context, repo, discussions, Discussion));
+        // a connection that ran out of pages keeps its cursor, so its empty page is not taken for a fresh start
+        if issues_pending {
+            issues_pending = repo.issues.page_info.has_next_page;
+            issue_cursor = repo.issues.page_info.end_cursor.take();
+            tasks.extend(fetch_tasks!(context, coverage, repo, issues, Issue));
+        }
+        if pull_requests_pending {
+            pull_requests_pending = repo.pull_requests.page_info.has_next_page;
+            pull_request_cursor = repo.pull_requests.page_info.end_cursor.take();
+            tasks.extend(fetch_tasks!(context, coverage, repo, pull_requests, Pr));
+        }
+        if discussions_pending {
+            discussions_pending = repo.discussions.page_info.has_next_page;
+            discussion_cursor = repo.discussions.page_info.end_cursor.take();
+            tasks.extend(fetch_tasks!(context, coverage, repo, discussions, Discussion));
+        }
 
-        issue_cursor = repo.issues.page_info.end_cursor;
-        pull_request_cursor = repo.pull_requests.page_info.end_cursor;
-        discussion_cursor = repo.discussions.page_info.end_cursor;
-        if !repo.issues.page_info.has_next_page || repo.pull_requests.page_info.has_next_page || repo.discussions.page_info.has_next_page {
+        if !(issues_pending || pull_requests_pending || discussions_pending) {
             break;
         }
     }
     tasks.sort_by_key(|task| Reverse(task.created_at));
     let project = Project {
+        node_id: repo.id,
         url: repo.url,
-        name: name.to_string(),
-        owner: owner.to_string(),
+        name: repo.name,
+        owner: repo.owner.login,
         tasks,
+        activity_watermark: None,
+        first_seen_at: None,
     };
 
     Ok(project)
 }
 
-pub async fn fetch_all_projects(context: &GithubClientContext) -> Result<Vec<Project>, Box<dyn Error>> {
-    let repos = &fetch_all_repos(context).await?;
+/// Returns the repositories to check and whether they came from the cache.
+async fn discover_repos(context: &GithubClientContext) -> Result<(Vec<Repo>, bool), Box<dyn Error>> {
+    let Some(cache) = &context.repository_cache else {
+        return Ok((fetch_all_repos(context).await?, false));
+    };
+    if !cache.force_refresh.load(Ordering::Relaxed)
+        && let Some(cached) = cache.read()
+        && cached.discovered_at > Utc::now() - cache.refresh_interval {
+        return Ok((cached.repos, true));
+    }
+
+    let discovered_at = Utc::now();
+    let repos = fetch_all_repos(context).await?;
+    cache.force_refresh.store(false, Ordering::Relaxed);
+    let cached = CachedRepos { discovered_at, repos };
+    if let Err(e) = cache.write(&cached) {
+        eprintln!("Failed to write repository cache: {}", e);
+    }
+    Ok((cached.repos, false))
+}
+
+async fn fetch_repo_activity(context: &GithubClientContext, ids: &[String]) -> Result<HashMap<String, DateTime>, Box<dyn Error>> {
+    let variables = repo_activity_query::Variables { ids: ids.to_vec() };
+    let result = run_query::<_, repo_activity_query::ResponseData>(context, RepoActivityQuery::build_query(variables)).await?;
+    let activity = result.nodes
+        .into_iter()
+        .flatten()
+        .flat_map(|node| match node {
+            repo_activity_query::RepoActivityQueryNodes::Repository(repo) => {
+                // pushes and repository settings don't touch tasks, so only the most recently updated task counts
+                let latest = repo.issues.nodes.iter().flatten().flatten().map(|node| node.updated_at)
+                    .chain(repo.pull_requests.nodes.iter().flatten().flatten().map(|node| node.updated_at))
+                    .chain(repo.discussions.nodes.iter().flatten().flatten().map(|node| node.updated_at))
+                    .max()
+                    .unwrap_or(repo.updated_at);
+                Some((repo.id, latest))
+            },
+            _ => None,
+        })
+        .collect();
+    Ok(activity)
+}
+
+/// Fetches the latest activity of all given repositories in bulk, which is a lot cheaper than fetching their tasks.
+async fn fetch_all_repo_activity(context: &GithubClientContext, repos: &[Repo]) -> Result<HashMap<String, DateTime>, Box<dyn Error>> {
+    let ids: Vec<String> = repos.iter()
+        .filter(|repo| !repo.id.is_empty())
+        .map(|repo| repo.id.clone())
+        .collect();
     let mut futures = Vec::new();
-    for repo in repos {
-        futures.push(fetch_project(context, repo.owner.as_str(), repo.name.as_str()));
+    for chunk in ids.chunks(NODES_PER_QUERY) {
+        futures.push(fetch_repo_activity(context, chunk));
     }
+    let results: Result<Vec<HashMap<String, DateTime>>, Box<dyn Error>> =
+        join_all(futures).await.into_iter().collect();
+    Ok(results?.into_iter().flatten().collect())
+}
 
-    join_all(futures).await.into_iter()
-        .flat_map(|result| {
-            match result {
-                Ok(project) if project.tasks.is_empty() => None,
-                other => Some(other),
-            }
+async fn fetch_all_projects(context: &GithubClientContext, coverage: &Coverage, known: &[Project]) -> Result<FetchResult, Box<dyn Error>> {
+    let (repos, cached) = discover_repos(context).await?;
+    let activity = match context.skip_unchanged {
+        true => fetch_all_repo_activity(context, &repos).await?,
+        false => HashMap::new(),
+    };
+    let watermarks: HashMap<&str, DateTime> = known.iter()
+        .flat_map(|project| project.activity_watermark.map(|watermark| (project.node_id.as_str(), watermark)))
+        .collect();
+
+    let mut unchanged = Vec::new();
+    let mut futures = Vec::new();
+    for repo in repos.iter() {
+        let latest_activity = activity.get(&repo.id).copied();
+        match (latest_activity, watermarks.get(repo.id.as_str())) {
+            (Some(latest_activity), Some(watermark)) if latest_activity <= *watermark => unchanged.push(repo.id.clone()),
+            _ => futures.push(async move {
+                let mut project = fetch_project(context, coverage, repo.owner.as_str(), repo.name.as_str()).await?;
+                project.activity_watermark = latest_activity;
+                Ok::<_, Box<dyn Error>>(project)
+            }),
+        }
+    }
+
+    let result = join_all(futures).await.into_iter().collect();
+
+    // cached repositories might have been deleted in the meantime
+    if cached && let (Err(_), Some(cache)) = (&result, &context.repository_cache) {
+        cache.force_refresh.store(true, Ordering::Relaxed);
+    }
+    Ok(FetchResult { projects: result?, complete: true, unchanged })
+}
+
+
+// the search index lags behind slightly, so searches reach back a bit before the last cycle
+const SEARCH_OVERLAP: Duration = Duration::from_mins(5);
+// GitHub rejects search queries longer than this
+const SEARCH_QUERY_MAX_LENGTH: usize = 256;
+const NODES_PER_QUERY: usize = 100;
+
+type TaskNode = (nodes_query::TaskRepository, Task);
+
+fn build_search_queries(base: &str, qualifiers: &[String]) -> Vec<String> {
+    let mut queries: Vec<String> = Vec::new();
+    let mut current = base.to_string();
+    let mut has_qualifier = false;
+    for qualifier in qualifiers {
+        if has_qualifier && current.len() + qualifier.len() + 1 > SEARCH_QUERY_MAX_LENGTH {
+            queries.push(current);
+            current = base.to_string();
+        }
+        current.push(' ');
+        current.push_str(qualifier);
+        has_qualifier = true;
+    }
+    if has_qualifier {
+        queries.push(current);
+    }
+    queries
+}
+
+async fn search_node_ids(context: &GithubClientContext, query: String, search_type: search_query::SearchType) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut output: Vec<String> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let variables = search_query::Variables { query: query.clone(), type_: search_type.clone(), cursor: cursor.clone() };
+        let result = run_query::<_, search_query::ResponseData>(context, SearchQuery::build_query(variables)).await?;
+        let values = result.search.nodes
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|node| match node {
+                search_query::SearchQuerySearchNodes::Issue(issue) => Some(issue.id),
+                search_query::SearchQuerySearchNodes::PullRequest(pull_request) => Some(pull_request.id),
+                search_query::SearchQuerySearchNodes::Discussion(discussion) => Some(discussion.id),
+                _ => None,
+            });
+
+        output.extend(values);
+
+        let page_info = result.search.page_info;
+        cursor = page_info.end_cursor;
+        if !page_info.has_next_page {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+macro_rules! task_from_node {
+    ($context:expr, $coverage:expr, $node:expr, $type:ident) => {{
+        let node = $node;
+        let covered = node.is_covered($coverage);
+        let author = node.author.map(|author| author.login).unwrap_or("<deleted user>".to_string());
+        if node.viewer_subscription == Some(nodes_query::SubscriptionState::SUBSCRIBED) || author == $context.username || covered {
+            None
+        } else {
+            let task = Task { node_id: node.id, observed_at: Utc::now(), task_type: TaskType::$type, id: node.number, author, url: node.url, title: node.title, created_at: node.created_at, updated_at: Some(node.updated_at), history: NotificationHistory::default(), missing_since: None };
+            Some((node.repository, task))
+        }
+    }};
+}
+
+impl PartialEq for nodes_query::SubscriptionState {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (nodes_query::SubscriptionState::SUBSCRIBED, nodes_query::SubscriptionState::SUBSCRIBED)
+                | (nodes_query::SubscriptionState::UNSUBSCRIBED, nodes_query::SubscriptionState::UNSUBSCRIBED)
+                | (nodes_query::SubscriptionState::IGNORED, nodes_query::SubscriptionState::IGNORED)
+        )
+    }
+}
+
+async fn fetch_task_nodes(context: &GithubClientContext, coverage: &Coverage, ids: &[String]) -> Result<Vec<TaskNode>, Box<dyn Error>> {
+    let variables = nodes_query::Variables {
+        ids: ids.to_vec(),
+        acknowledgement: context.acknowledgement.map(|reaction| reaction_content!(nodes_query, reaction)),
+        acknowledgement_limit: acknowledgement_limit(context),
+        engagement_limit: engagement_limit(coverage),
+    };
+    let result = run_query::<_, nodes_query::ResponseData>(context, NodesQuery::build_query(variables)).await?;
+    let tasks = result.nodes
+        .into_iter()
+        .flatten()
+        .flat_map(|node| match node {
+            nodes_query::NodesQueryNodes::Issue(issue) => task_from_node!(context, coverage, issue, Issue),
+            nodes_query::NodesQueryNodes::PullRequest(pull_request) => task_from_node!(context, coverage, pull_request, Pr),
+            nodes_query::NodesQueryNodes::Discussion(discussion) => task_from_node!(context, coverage, discussion, Discussion),
+            _ => None,
         })
-        .collect()
+        .collect();
+    Ok(tasks)
+}
+
+/// Finds open tasks created after `since` in all repositories owned by the viewer or by an organization the viewer
+/// administers, and in the other discovered repositories the viewer collaborates on. Other than [fetch_all_projects]
+/// this only returns the new tasks, not all open ones.
+async fn search_projects(context: &GithubClientContext, coverage: &Coverage, since: DateTime) -> Result<Vec<Project>, Box<dyn Error>> {
+    let orgas = fetch_viewer_organizations(context).await?;
+    let (repos, _) = discover_repos(context).await?;
+    let is_covered_owner = |owner: &str| owner.eq_ignore_ascii_case(&context.username)
+        || orgas.iter().any(|orga| owner.eq_ignore_ascii_case(orga));
+    let owners: Vec<String> = std::iter::once(format!("user:{}", context.username))
+        .chain(orgas.iter().map(|orga| format!("org:{}", orga)))
+        .chain(repos.iter()
+            .filter(|repo| !is_covered_owner(&repo.owner))
+            .map(|repo| format!("repo:{}/{}", repo.owner, repo.name)))
+        .collect();
+    let base_query = format!(
+        "is:open archived:false created:>{} -author:{}",
+        since.to_rfc3339_opts(SecondsFormat::Secs, true),
+        context.username,
+    );
+
+    let mut futures = Vec::new();
+    for query in build_search_queries(base_query.as_str(), &owners) {
+        futures.push(search_node_ids(context, query.clone(), search_query::SearchType::ISSUE));
+        futures.push(search_node_ids(context, query, search_query::SearchType::DISCUSSION));
+    }
+    let results: Result<Vec<Vec<String>>, Box<dyn Error>> =
+        join_all(futures).await.into_iter().collect();
+    let ids: Vec<String> = results?.into_iter().flatten().collect();
+
+    fetch_projects_by_node_ids(context, coverage, ids).await
+}
+
+/// Looks up the given issue, pull request and discussion node IDs and groups the resulting tasks by project. Tasks the
+/// viewer is subscribed to, authored or acknowledged, or that maintainers engaged with, are dropped, just like in [fetch_all_projects].
+async fn fetch_projects_by_node_ids(context: &GithubClientContext, coverage: &Coverage, mut ids: Vec<String>) -> Result<Vec<Project>, Box<dyn Error>> {
+    ids.sort();
+    ids.dedup();
+
+    let mut futures = Vec::new();
+    for chunk in ids.chunks(NODES_PER_QUERY) {
+        futures.push(fetch_task_nodes(context, coverage, chunk));
+    }
+    let results: Result<Vec<Vec<TaskNode>>, Box<dyn Error>> =
+        join_all(futures).await.into_iter().collect();
+
+    let mut projects: Vec<Project> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (repository, task) in results?.into_iter().flatten() {
+        match positions.get(&repository.id) {
+            Some(position) => projects[*position].tasks.push(task),
+            None => {
+                positions.insert(repository.id.clone(), projects.len());
+                projects.push(Project {
+                    node_id: repository.id,
+                    url: repository.url,
+                    name: repository.name,
+                    owner: repository.owner.login,
+                    tasks: vec![task],
+                    activity_watermark: None,
+                    first_seen_at: None,
+                });
+            },
+        }
+    }
+    for project in projects.iter_mut() {
+        project.tasks.sort_by_key(|task| Reverse(task.created_at));
+    }
+
+    Ok(projects)
+}
+
+async fn fetch_team_members(context: &GithubClientContext, team: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let (organization, slug) = team.split_once('/').ok_or("maintainer team expects organization/team-slug")?;
+    let mut output: Vec<String> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let variables = team_members_query::Variables { organization: organization.to_string(), slug: slug.to_string(), cursor: cursor.clone() };
+        let result = run_query::<_, team_members_query::ResponseData>(context, TeamMembersQuery::build_query(variables)).await?;
+        let members = result.organization.ok_or("no organization")?
+            .team.ok_or("no team")?
+            .members;
+        output.extend(members.nodes.into_iter().flatten().flatten().map(|member| member.login));
+
+        cursor = members.page_info.end_cursor;
+        if !members.page_info.has_next_page {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+/// Resolves the configured maintainers, team membership is looked up again on every fetch.
+async fn resolve_coverage(context: &GithubClientContext) -> Result<Coverage, Box<dyn Error>> {
+    let mut maintainers: HashSet<String> = context.maintainers.logins.iter().cloned().collect();
+    if let Some(team) = &context.maintainers.team {
+        maintainers.extend(fetch_team_members(context, team.as_str()).await?);
+    }
+    let acknowledging = maintainers.iter().cloned()
+        .chain(std::iter::once(context.username.clone()))
+        .collect();
+    Ok(Coverage { maintainers, acknowledging })
+}
+
+impl Forge for GithubClientContext {
+    fn fetch_projects<'a>(&'a self, since: Option<DateTime>, known: &'a [Project]) -> LocalBoxFuture<'a, Result<FetchResult, Box<dyn Error>>> {
+        async move {
+            let coverage = resolve_coverage(self).await?;
+            match (self.fetch_strategy, since) {
+                (FetchStrategy::Search, Some(since)) => Ok(FetchResult { projects: search_projects(self, &coverage, since - SEARCH_OVERLAP).await?, complete: false, unchanged: Vec::new() }),
+                _ => fetch_all_projects(self, &coverage, known).await,
+            }
+        }.boxed_local()
+    }
+
+    fn fetch_projects_by_node_ids(&self, node_ids: &[String]) -> LocalBoxFuture<'_, Result<Vec<Project>, Box<dyn Error>>> {
+        let node_ids = node_ids.to_vec();
+        async move {
+            let coverage = resolve_coverage(self).await?;
+            let mut projects = fetch_projects_by_node_ids(self, &coverage, node_ids).await?;
+            // the token might be able to read tasks of repositories that belong to other identities, e.g. public ones
+            let (repos, _) = discover_repos(self).await?;
+            // repositories cached before node IDs were tracked are only known by name
+            let monitored: HashSet<String> = repos.into_iter()
+                .flat_map(|repo| [repo.id, format!("{}/{}", repo.owner, repo.name)])
+                .collect();
+            projects.retain(|project| monitored.contains(&project.node_id) || monitored.contains(&format!("{}/{}", project.owner, project.name)));
+            Ok(projects)
+        }.boxed_local()
+    }
 }
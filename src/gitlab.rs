@@ -0,0 +1,361 @@
+use std::error::Error;
+use std::cmp::Reverse;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use futures::future::join_all;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use reqwest::Url;
+use crate::error::QueryError;
+use crate::forge::{DateTime, FetchResult, Forge, NotificationHistory, Project, Task, TaskType};
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+
+/// Upper bound of subscription lookups in flight per task listing.
+const SUBSCRIPTION_CONCURRENCY: usize = 8;
+
+pub struct GitlabClientContext {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub username: String,
+    pub access_token: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Namespace {
+    full_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repo {
+    id: i64,
+    path: String,
+    web_url: String,
+    namespace: Namespace,
+    #[serde(default)]
+    archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationSettings {
+    level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subject {
+    id: i64,
+    iid: i64,
+    title: String,
+    created_at: DateTime,
+    updated_at: DateTime,
+    web_url: String,
+    author: Option<Author>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    #[serde(default)]
+    subscribed: bool,
+}
+
+fn api_url(context: &GitlabClientContext, segments: &[&str]) -> Result<Url, Box<dyn Error>> {
+    let mut url = Url::parse(context.base_url.as_str())?;
+    url.path_segments_mut()
+        .map_err(|_| "invalid GitLab base URL")?
+        .pop_if_empty()
+        .extend(["api", "v4"])
+        .extend(segments);
+    Ok(url)
+}
+
+async fn get_page<Res>(context: &GitlabClientContext, url: Url) -> Result<(Res, Option<String>), Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let response = context.client.get(url)
+        .header("PRIVATE-TOKEN", context.access_token.as_str())
+        .header("User-Agent", "ProjectMonitor")
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Box::new(QueryError::HttpError(status.as_u16())));
+    }
+    let next_page = response.headers()
+        .get("X-Next-Page")
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string());
+    let bytes = response.bytes().await?;
+    Ok((serde_json::from_slice(&bytes)?, next_page))
+}
+
+async fn get<Res>(context: &GitlabClientContext, segments: &[&str]) -> Result<Res, Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let (result, _) = get_page(context, api_url(context, segments)?).await?;
+    Ok(result)
+}
+
+async fn get_all<Res>(context: &GitlabClientContext, base_url: Url) -> Result<Vec<Res>, Box<dyn Error>>
+    where
+        Res: DeserializeOwned {
+    let mut output: Vec<Res> = Vec::new();
+    let mut page = "1".to_string();
+    loop {
+        let mut url = base_url.clone();
+        url.query_pairs_mut()
+            .append_pair("per_page", "100")
+            .append_pair("page", page.as_str());
+        let (values, next_page) = get_page::<Vec<Res>>(context, url).await?;
+        output.extend(values);
+
+        match next_page {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+    Ok(output)
+}
+
+async fn fetch_all_repos(context: &GitlabClientContext) -> Result<Vec<Repo>, Box<dyn Error>> {
+    let mut futures = Vec::new();
+    let mut user_url = api_url(context, &["users", context.username.as_str(), "projects"])?;
+    user_url.query_pairs_mut().append_pair("archived", "false");
+    futures.push(get_all::<Repo>(context, user_url));
+    for group in context.groups.iter() {
+        let mut group_url = api_url(context, &["groups", group.as_str(), "projects"])?;
+        group_url.query_pairs_mut()
+            .append_pair("archived", "false")
+            .append_pair("include_subgroups", "true");
+        futures.push(get_all::<Repo>(context, group_url));
+    }
+
+    let results: Result<Vec<Vec<Repo>>, Box<dyn Error>> =
+        join_all(futures).await.into_iter().collect();
+
+    let mut repos: Vec<Repo> = results?.into_iter()
+        .flatten()
+        .filter(|repo| !repo.archived)
+        .collect();
+    repos.sort_by_key(|repo| repo.id);
+    repos.dedup_by_key(|repo| repo.id);
+    Ok(repos)
+}
+
+async fn is_subscribed(context: &GitlabClientContext, project_id: &str, collection: &str, iid: i64) -> Result<(i64, bool), Box<dyn Error>> {
+    let iid_segment = iid.to_string();
+    let subscription: Subscription = get(context, &["projects", project_id, collection, iid_segment.as_str()]).await?;
+    Ok((iid, subscription.subscribed))
+}
+
+async fn fetch_tasks(context: &GitlabClientContext, project_id: &str, collection: &str, task_type: TaskType, global_id_type: &str) -> Result<Vec<Task>, Box<dyn Error>> {
+    let mut url = api_url(context, &["projects", project_id, collection])?;
+    url.query_pairs_mut().append_pair("state", "opened");
+    let subjects: Vec<Subject> = get_all(context, url).await?;
+    let subjects: Vec<Subject> = subjects.into_iter()
+        .filter(|subject| subject.author.as_ref().map(|author| author.username.as_str()) != Some(context.username.as_str()))
+        .collect();
+
+    // the subscription state is only part of the individual resources, not of the listing
+    let subscribed: HashSet<i64> = stream::iter(subjects.iter())
+        .map(|subject| is_subscribed(context, project_id, collection, subject.iid))
+        .buffer_unordered(SUBSCRIPTION_CONCURRENCY)
+        .try_filter_map(|(iid, subscribed)| async move { Ok(subscribed.then_some(iid)) })
+        .try_collect()
+        .await?;
+
+    let tasks = subjects.into_iter()
+        .filter(|subject| !subscribed.contains(&subject.iid))
+        .map(|subject| Task {
+            node_id: format!("gid://gitlab/{}/{}", global_id_type, subject.id),
+            observed_at: Utc::now(),
+            task_type: task_type.clone(),
+            id: subject.iid,
+            title: subject.title,
+            created_at: subject.created_at,
+            updated_at: Some(subject.updated_at),
+            history: NotificationHistory::default(),
+            missing_since: None,
+            url: subject.web_url,
+            author: subject.author.map(|author| author.username).unwrap_or("<deleted user>".to_string()),
+        })
+        .collect();
+    Ok(tasks)
+}
+
+async fn fetch_project(context: &GitlabClientContext, repo: &Repo) -> Result<Project, Box<dyn Error>> {
+    let project_id = repo.id.to_string();
+    let settings: NotificationSettings = get(context, &["projects", project_id.as_str(), "notification_settings"]).await?;
+
+    // watching a project subscribes to everything happening in it
+    let mut tasks: Vec<Task> = Vec::new();
+    if settings.level != "watch" {
+        tasks.extend(fetch_tasks(context, project_id.as_str(), "issues", TaskType::Issue, "Issue").await?);
+        tasks.extend(fetch_tasks(context, project_id.as_str(), "merge_requests", TaskType::Pr, "MergeRequest").await?);
+    }
+    tasks.sort_by_key(|task| Reverse(task.created_at));
+
+    Ok(Project {
+        node_id: format!("gid://gitlab/Project/{}", repo.id),
+        name: repo.path.clone(),
+        owner: repo.namespace.full_path.clone(),
+        url: repo.web_url.clone(),
+        tasks,
+        activity_watermark: None,
+        first_seen_at: None,
+    })
+}
+
+async fn fetch_all_projects(context: &GitlabClientContext) -> Result<Vec<Project>, Box<dyn Error>> {
+    let repos = &fetch_all_repos(context).await?;
+    let mut futures = Vec::new();
+    for repo in repos {
+        futures.push(fetch_project(context, repo));
+    }
+
+    join_all(futures).await.into_iter().collect()
+}
+
+impl Forge for GitlabClientContext {
+    fn fetch_projects<'a>(&'a self, _since: Option<DateTime>, _known: &'a [Project]) -> LocalBoxFuture<'a, Result<FetchResult, Box<dyn Error>>> {
+        async move {
+            Ok(FetchResult { projects: fetch_all_projects(self).await?, complete: true, unchanged: Vec::new() })
+        }.boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use http_body_util::Full;
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Request, Response, StatusCode};
+    use hyper_util::rt::TokioIo;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    const ISSUE_COUNT: i64 = 20;
+
+    #[derive(Default)]
+    struct Lookups {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        total: AtomicUsize,
+    }
+
+    fn repo(id: i64, path: &str, archived: bool) -> Value {
+        json!({ "id": id, "path": path, "web_url": format!("https://gitlab.test/acme/{path}"), "namespace": { "full_path": "acme" }, "archived": archived })
+    }
+
+    fn issue(iid: i64, author: &str) -> Value {
+        json!({
+            "id": 1000 + iid,
+            "iid": iid,
+            "title": format!("Issue {iid}"),
+            "created_at": format!("2024-01-01T00:00:{iid:02}Z"),
+            "updated_at": "2024-01-02T00:00:00Z",
+            "web_url": format!("https://gitlab.test/acme/widgets/-/issues/{iid}"),
+            "author": { "username": author },
+        })
+    }
+
+    fn json_response(body: Value, next_page: &str) -> Response<Full<Bytes>> {
+        Response::builder()
+            .header("Content-Type", "application/json")
+            .header("X-Next-Page", next_page)
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    async fn handle(request: Request<Incoming>, lookups: &Lookups) -> Response<Full<Bytes>> {
+        let page = request.uri().query().unwrap_or_default().split('&')
+            .find_map(|pair| pair.strip_prefix("page="))
+            .unwrap_or("1")
+            .to_string();
+        let segments: Vec<&str> = request.uri().path().trim_start_matches("/api/v4/").split('/').collect();
+        match (segments.as_slice(), page.as_str()) {
+            (["users", "alice", "projects"], _) => json_response(json!([repo(1, "widgets", false), repo(2, "archive", true)]), ""),
+            (["groups", "team", "projects"], "1") => json_response(json!([repo(3, "watched", false)]), "2"),
+            (["groups", "team", "projects"], _) => json_response(json!([repo(1, "widgets", false)]), ""),
+            (["projects", "1", "notification_settings"], _) => json_response(json!({ "level": "global" }), ""),
+            (["projects", "3", "notification_settings"], _) => json_response(json!({ "level": "watch" }), ""),
+            (["projects", "1", "issues"], _) => {
+                let mut issues: Vec<Value> = (1..ISSUE_COUNT).map(|iid| issue(iid, "bob")).collect();
+                issues.push(issue(ISSUE_COUNT, "alice"));
+                json_response(Value::Array(issues), "")
+            },
+            (["projects", "1", "merge_requests"], _) => json_response(json!([]), ""),
+            (["projects", "1", "issues", iid], _) => {
+                let iid: i64 = iid.parse().unwrap();
+                let in_flight = lookups.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                lookups.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                lookups.total.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                lookups.in_flight.fetch_sub(1, Ordering::SeqCst);
+                json_response(json!({ "subscribed": iid % 3 == 0 }), "")
+            },
+            _ => Response::builder().status(StatusCode::NOT_FOUND).body(Full::new(Bytes::new())).unwrap(),
+        }
+    }
+
+    async fn serve(lookups: Arc<Lookups>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let lookups = lookups.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(|request| {
+                        let lookups = lookups.clone();
+                        async move { Ok::<_, hyper::Error>(handle(request, &lookups).await) }
+                    });
+                    let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+                });
+            }
+        });
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn fetches_unsubscribed_tasks_with_bounded_lookups() {
+        let lookups = Arc::new(Lookups::default());
+        let context = GitlabClientContext {
+            client: reqwest::Client::new(),
+            base_url: serve(lookups.clone()).await,
+            username: "alice".to_string(),
+            access_token: "token".to_string(),
+            groups: vec!["team".to_string()],
+        };
+
+        let result = context.fetch_projects(None, &[]).await.unwrap();
+
+        let mut projects = result.projects;
+        projects.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        let node_ids: Vec<&str> = projects.iter().map(|project| project.node_id.as_str()).collect();
+        assert_eq!(node_ids, ["gid://gitlab/Project/1", "gid://gitlab/Project/3"]);
+        assert!(projects[1].tasks.is_empty(), "watched projects have no unsubscribed tasks");
+
+        let mut iids: Vec<i64> = projects[0].tasks.iter().map(|task| task.id).collect();
+        iids.sort();
+        let expected: Vec<i64> = (1..ISSUE_COUNT).filter(|iid| iid % 3 != 0).collect();
+        assert_eq!(iids, expected);
+        assert_eq!(projects[0].tasks[0].node_id, "gid://gitlab/Issue/1019");
+
+        assert_eq!(lookups.total.load(Ordering::SeqCst), (ISSUE_COUNT - 1) as usize);
+        let max_in_flight = lookups.max_in_flight.load(Ordering::SeqCst);
+        assert!(max_in_flight > 1 && max_in_flight <= SUBSCRIPTION_CONCURRENCY, "{max_in_flight} lookups in flight");
+    }
+}
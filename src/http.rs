@@ -0,0 +1,37 @@
+use std::error::Error;
+use reqwest::{Certificate, NoProxy, Proxy};
+
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Comma separated hosts, domains and networks to connect to directly, in the usual NO_PROXY format.
+    pub no_proxy: Option<String>,
+}
+
+pub struct NetworkConfig {
+    pub proxy: Option<ProxyConfig>,
+    /// PEM encoded certificates to trust in addition to the built-in roots.
+    pub ca_bundle: Option<Vec<u8>>,
+}
+
+pub fn create_http_client(config: &NetworkConfig) -> Result<reqwest::Client, Box<dyn Error>> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy_config) = &config.proxy {
+        let mut proxy = Proxy::all(proxy_config.url.as_str())?
+            .no_proxy(proxy_config.no_proxy.as_deref().and_then(NoProxy::from_string));
+        if let Some(username) = &proxy_config.username {
+            proxy = proxy.basic_auth(username.as_str(), proxy_config.password.as_deref().unwrap_or(""));
+        }
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_bundle) = &config.ca_bundle {
+        for certificate in Certificate::from_pem_bundle(ca_bundle)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use crate::forge::{node_key, Project, Task};
+
+/// Projects and their tasks with hash indices over their identities, so a cycle does not scan the whole state for
+/// every project and task it looks up. Projects and tasks keep their order, so the state serializes the same way
+/// every time.
+#[derive(Default)]
+pub struct ProjectIndex {
+    projects: Vec<Project>,
+    positions: HashMap<String, usize>,
+    /// Positions of the tasks within each project, in the same order as the projects.
+    task_positions: Vec<HashMap<String, usize>>,
+}
+
+impl ProjectIndex {
+    pub fn new(projects: Vec<Project>) -> Self {
+        let mut index = ProjectIndex { projects, ..Default::default() };
+        index.reindex();
+        index
+    }
+
+    fn reindex(&mut self) {
+        self.positions.clear();
+        self.task_positions.clear();
+        for (position, project) in self.projects.iter().enumerate() {
+            self.positions.insert(node_key(&project.node_id, &project.url).to_string(), position);
+            self.task_positions.push(project.tasks.iter()
+                .enumerate()
+                .map(|(position, task)| (node_key(&task.node_id, &task.url).to_string(), position))
+                .collect());
+        }
+    }
+
+    fn position(&self, subject: &Project) -> Option<usize> {
+        self.positions.get(node_key(&subject.node_id, &subject.url)).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Project> {
+        self.projects.iter()
+    }
+
+    /// The identities of the projects and tasks must not be changed, as they would no longer be found.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Project> {
+        self.projects.iter_mut()
+    }
+
+    pub fn project(&self, subject: &Project) -> Option<&Project> {
+        self.position(subject).map(|position| &self.projects[position])
+    }
+
+    /// The identity of the project and its tasks must not be changed, as they would no longer be found.
+    pub fn project_mut(&mut self, subject: &Project) -> Option<&mut Project> {
+        self.position(subject).map(|position| &mut self.projects[position])
+    }
+
+    pub fn task(&self, project: &Project, subject: &Task) -> Option<&Task> {
+        let position = self.position(project)?;
+        let task_position = self.task_positions[position].get(node_key(&subject.node_id, &subject.url))?;
+        Some(&self.projects[position].tasks[*task_position])
+    }
+
+    pub fn push(&mut self, project: Project) {
+        self.positions.insert(node_key(&project.node_id, &project.url).to_string(), self.projects.len());
+        self.task_positions.push(project.tasks.iter()
+            .enumerate()
+            .map(|(position, task)| (node_key(&task.node_id, &task.url).to_string(), position))
+            .collect());
+        self.projects.push(project);
+    }
+
+    /// Adds the task to its project, which is added with only this task if it is not present yet. Returns whether
+    /// the task was added.
+    pub fn upsert_task(&mut self, project: &Project, task: &Task) -> bool {
+        let Some(position) = self.position(project) else {
+            self.push(Project { tasks: vec![task.clone()], ..project.clone() });
+            return true;
+        };
+        let tasks = &mut self.projects[position].tasks;
+        let key = node_key(&task.node_id, &task.url);
+        if self.task_positions[position].contains_key(key) {
+            return false;
+        }
+        self.task_positions[position].insert(key.to_string(), tasks.len());
+        tasks.push(task.clone());
+        true
+    }
+
+    /// Keeps the projects the predicate holds for, it may also drop tasks of the projects.
+    pub fn retain(&mut self, predicate: impl FnMut(&mut Project) -> bool) {
+        self.projects.retain_mut(predicate);
+        self.reindex();
+    }
+
+    pub fn into_projects(self) -> Vec<Project> {
+        self.projects
+    }
+}
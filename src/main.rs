@@ -1,50 +1,195 @@
 extern crate core;
 
 use crate::email::TransportSecurity::StartTls;
-use crate::email::{create_email_client, send_email, EmailContext, TransportSecurity};
-use crate::github::{Project, Task, TaskType};
+use crate::email::{create_email_client, send_email, Delivery, EmailContext, TransportSecurity};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::forge::{FetchResult, Forge, InMemoryForge, Project, Task, TaskType};
+use crate::cassette::{Cassette, CassetteMode};
+use crate::export::{export_rows, write_export, ExportFilter, ExportFormat};
+use crate::github::{FetchStrategy, Maintainers, Reaction, RepositoryCache};
+use crate::http::{create_http_client, NetworkConfig, ProxyConfig};
+use crate::index::ProjectIndex;
+use crate::persistence::{Mute, Persistence, State};
+use crate::template::{Cycle, Templates};
 use core::time::Duration;
 use github::GithubClientContext;
+use gitlab::GitlabClientContext;
+use gitea::GiteaClientContext;
 use lettre::transport::smtp::SUBMISSION_PORT;
 use lettre::Address;
 use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use iso8601::duration;
+use serde::Serialize;
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc::unbounded_channel;
 use tokio::{select, task};
+use webhook::WebhookConfig;
 
+mod audit;
+#[cfg(test)]
+mod bench;
+mod cassette;
+mod forge;
 mod github;
+mod http;
+mod index;
+mod gitea;
+mod gitlab;
 mod email;
 mod error;
+mod export;
+mod persistence;
+mod sqlite;
+mod template;
+mod webhook;
+
+enum TaskSource {
+    /// A regular cycle, which continues from the last successful cycle recorded in the state of each identity.
+    Poll,
+    /// Node IDs of tasks received by the webhook receiver.
+    Webhook(Vec<String>),
+}
+
+/// Per task type age after which a task without response is considered stale.
+struct StaleThresholds {
+    issue: Option<Duration>,
+    pr: Option<Duration>,
+    discussion: Option<Duration>,
+}
+
+impl StaleThresholds {
+    fn for_type(&self, task_type: &TaskType) -> Option<Duration> {
+        match task_type {
+            TaskType::Issue => self.issue,
+            TaskType::Pr => self.pr,
+            TaskType::Discussion => self.discussion,
+        }
+    }
+}
+
+/// How reminders about a task are repeated.
+enum ReminderCadence {
+    Never,
+    Interval(Duration),
+    /// Doubles the interval after every reminder.
+    Backoff(Duration),
+}
+
+impl ReminderCadence {
+    /// Interval between the previous and the next reminder, given the number of reminders sent so far.
+    fn interval(&self, reminder_count: u32) -> Option<Duration> {
+        match self {
+            ReminderCadence::Never => None,
+            ReminderCadence::Interval(interval) => Some(*interval),
+            ReminderCadence::Backoff(interval) => 2u32.checked_pow(reminder_count)
+                .and_then(|factor| interval.checked_mul(factor)),
+        }
+    }
+}
+
+struct Baseline {
+    /// Tasks created before the cutoff are recorded silently as well.
+    cutoff: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct NotificationPolicy {
+    /// How long tasks that are no longer open are remembered, so they are not reported again when reopened.
+    closed_retention: Duration,
+    stale_after: StaleThresholds,
+    reminder_cadence: ReminderCadence,
+    max_reminders: Option<u32>,
+    /// Records open tasks that predate the first sighting of their project silently, instead of reporting them.
+    baseline: Option<Baseline>,
+    /// How long polling cycles may only look for new tasks or skip projects without activity, before all open tasks are
+    /// fetched again, to notice closed tasks and changes that don't count as activity.
+    full_scan_interval: Duration,
+}
+
+impl NotificationPolicy {
+    /// The first reminder is due once the task turned stale, but no earlier than the stale threshold after it has been
+    /// reported, or after one cadence interval for task types without a stale threshold. Further reminders follow the
+    /// cadence.
+    fn reminder_due(&self, task: &Task, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let history = &task.history;
+        if history.baselined {
+            return false;
+        }
+        if self.max_reminders.is_some_and(|max_reminders| history.reminder_count >= max_reminders) {
+            return false;
+        }
+        let notified_at = history.first_notified_at.unwrap_or(task.observed_at);
+        let due_at = match history.last_reminded_at {
+            Some(last_reminded_at) => self.reminder_cadence.interval(history.reminder_count)
+                .map(|interval| last_reminded_at + interval),
+            None => match self.stale_after.for_type(&task.task_type) {
+                Some(stale_after) => Some(task.created_at.max(notified_at) + stale_after),
+                None => self.reminder_cadence.interval(0)
+                    .map(|interval| notified_at + interval),
+            },
+        };
+        due_at.is_some_and(|due_at| due_at <= now)
+    }
+}
+
+struct Identity {
+    /// Name of the identity, none for the single default identity.
+    name: Option<String>,
+    forges: Vec<Box<dyn Forge>>,
+    persistence_path: String,
+    /// SQLite database to keep the state in instead of the persistence file, which is only imported once then.
+    database_path: Option<String>,
+}
+
+struct IdentityReport<'a> {
+    identity: Option<&'a str>,
+    notify: &'a [Project],
+    remind: &'a [Project],
+    renamed: &'a [ProjectRename],
+}
 
 struct ResultingTasks {
     new_known: Vec<Project>,
     notify: Vec<Project>,
+    remind: Vec<Project>,
+    renamed: Vec<ProjectRename>,
+}
+
+/// Delivers the reports and keeps track of every delivery attempt.
+struct Notifier {
+    email_context: EmailContext,
+    templates: Templates,
+    audit_log: AuditLog,
+}
+
+#[derive(Serialize)]
+struct ProjectRename {
+    previous: String,
+    current: String,
+    url: String,
 }
 
 fn read_secret(name: &str) -> Option<String> {
     let direct_env_name = name.to_uppercase();
-    match std::env::var(&direct_env_name) {
-        Ok(result) => { return Some(result) },
-        Err(_) => {},
-    };
+    if let Ok(result) = std::env::var(&direct_env_name) {
+        return Some(result);
+    }
 
     let file_env_name = format!("{}_FILE", direct_env_name);
-    match std::env::var(file_env_name) {
-        Ok(result) => match fs::read_to_string(result) {
-            Ok(file_content) => { return Some(file_content) }
-            Err(_) => { }
-        }
-        Err(_) => {}
-    };
+    if let Ok(result) = std::env::var(file_env_name)
+        && let Ok(file_content) = fs::read_to_string(result) {
+        return Some(file_content);
+    }
 
-    match fs::read_to_string(format!("/run/secrets/{}", name)) {
-        Ok(file_content) => { return Some(file_content) }
-        Err(_) => {}
+    if let Ok(file_content) = fs::read_to_string(format!("/run/secrets/{}", name)) {
+        return Some(file_content);
     }
 
     None
@@ -60,145 +205,358 @@ fn read_required_secret(name: &str) -> String {
     }
 }
 
-fn read_known_tasks(file: &mut File) -> Result<Vec<Project>, Box<dyn Error>> {
-    let size = file.metadata()?.len();
-    if size == 0 {
-        return Ok(Vec::new());
+async fn find_issues_for_notification(identities: &[Identity], merge_notifications: bool, source: &TaskSource, policy: &NotificationPolicy, notifier: &mut Notifier) -> Result<(), Box<dyn Error>> {
+    if merge_notifications {
+        return find_issues_for_identities(identities, source, policy, notifier).await;
     }
-    file.seek(SeekFrom::Start(0))?;
-    match serde_json::from_reader::<_, Vec<Project>>(BufReader::new(file)) {
-        Ok(data) => Ok(data),
-        Err(e) => {
-            eprintln!("Failed to parse known tasks: {}", e);
-            Ok(Vec::new())
-        },
+
+    let mut result = Ok(());
+    for identity in identities {
+        if let Err(err) = find_issues_for_identities(std::slice::from_ref(identity), source, policy, notifier).await {
+            result = Err(err);
+        }
     }
+    result
 }
 
-fn write_known_tasks(file: &mut File, projects: &Vec<Project>) -> Result<(), Box<dyn Error>> {
-    file.seek(SeekFrom::Start(0))?;
+/// Checks all given identities and sends a single notification covering all of them. Identities that fail to be
+/// checked are left out, without holding back the others.
+async fn find_issues_for_identities(identities: &[Identity], source: &TaskSource, policy: &NotificationPolicy, notifier: &mut Notifier) -> Result<(), Box<dyn Error>> {
+    let now = chrono::Utc::now();
+    let mut checked = Vec::new();
+    let mut errors = Vec::new();
+    for identity in identities {
+        match check_identity(identity, source, now, policy).await {
+            Ok((persistence, state, complete, resulting_tasks)) => checked.push((identity, persistence, state, complete, resulting_tasks)),
+            Err(err) => errors.push(format!("{}: {}", identity.name.as_deref().unwrap_or("default"), err)),
+        }
+    }
 
-    file.set_len(0)?;
-    serde_json::to_writer_pretty(BufWriter::new(file), &projects)?;
-    Ok(())
+    let reports: Vec<IdentityReport> = checked.iter()
+        .map(|(identity, _, _, _, resulting_tasks)| IdentityReport {
+            identity: identity.name.as_deref(),
+            notify: &resulting_tasks.notify,
+            remind: &resulting_tasks.remind,
+            renamed: &resulting_tasks.renamed,
+        })
+        .collect();
+    let cycle = Cycle {
+        started_at: now,
+        source: match source {
+            TaskSource::Poll => "poll",
+            TaskSource::Webhook(_) => "webhook",
+        },
+        build: build_hash(),
+    };
+    // try notifying before writing the known tasks out, otherwise failed notifications will not be reattempted
+    notify_about_tasks(&reports, &cycle, notifier)?;
+
+    // the notification has been sent, so every identity has to be written, even if another one fails
+    for (identity, persistence, state, complete, ResultingTasks { mut new_known, .. }) in checked {
+        let last_successful_cycle = match source {
+            TaskSource::Poll => Some(now),
+            TaskSource::Webhook(_) => state.last_successful_cycle,
+        };
+        let last_complete_cycle = match complete {
+            true => Some(now),
+            false => state.last_complete_cycle,
+        };
+        let (mutes, expired): (Vec<Mute>, Vec<Mute>) = state.mutes.iter().cloned().partition(|mute| mute.is_active(now));
+        for mute in expired.iter() {
+            println!("Snooze of {} expired", mute.target);
+        }
+        forget_watermarks(&mut new_known, &expired);
+        let changed = state.projects != new_known || state.last_successful_cycle != last_successful_cycle
+            || state.last_complete_cycle != last_complete_cycle || state.mutes != mutes;
+        let written = match changed {
+            true => persistence.write(&State::new(new_known, last_successful_cycle, last_complete_cycle, mutes)),
+            false => Ok(()),
+        };
+        if let Err(err) = written.and_then(|_| persistence.unlock()) {
+            errors.push(format!("{}: {}", identity.name.as_deref().unwrap_or("default"), err));
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join(", ").into()),
+    }
 }
 
-async fn find_issues_for_notification(github_context: &GithubClientContext, retain_for: Duration, email_context: &mut EmailContext, persistence_path: &str) -> Result<(), Box<dyn Error>> {
-    let mut file = File::options()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(persistence_path)?;
+/// Locks the state of the identity and checks its tasks, the state stays locked until it has been written.
+async fn check_identity(identity: &Identity, source: &TaskSource, now: chrono::DateTime<chrono::Utc>, policy: &NotificationPolicy) -> Result<(Box<dyn Persistence>, State, bool, ResultingTasks), Box<dyn Error>> {
+    let persistence = persistence::lock(identity.persistence_path.as_str(), identity.database_path.as_deref())?;
 
-    file.lock()?;
+    let state = persistence.read()?;
+    // forges may only look for tasks created since the last cycle and skip projects without activity, until a full
+    // scan is due again, as not every change counts as activity, e.g. subscribing to a task
+    let full_scan_due = state.last_complete_cycle
+        .is_none_or(|last_complete_cycle| last_complete_cycle <= now - policy.full_scan_interval);
+    let (since, known) = match full_scan_due {
+        true => (None, &[][..]),
+        false => (state.last_successful_cycle, state.projects.as_slice()),
+    };
+    let fetched = fetch_tasks(&identity.forges, source, since, known).await?;
+    let complete = fetched.complete && fetched.unchanged.is_empty();
+    let resulting_tasks = check_tasks_against_persistence(fetched, now, policy, &state.projects, &state.mutes);
+    Ok((persistence, state, complete, resulting_tasks))
+}
 
-    let known_tasks = read_known_tasks(&mut file)?;
-    let ResultingTasks { new_known,  notify } = check_tasks_against_persistence(&github_context, retain_for, &known_tasks).await?;
-    // try notifying before writing the known tasks out, otherwise failed notifications will not be reattempted
-    notify_about_tasks(&notify, email_context)?;
-    if known_tasks != new_known {
-        write_known_tasks(&mut file, &new_known)?;
+/// Assigns node IDs to known projects and tasks persisted before node IDs were tracked, or under an outdated node ID scheme, by matching them on their URL.
+fn migrate_node_ids(known_tasks: &mut [Project], all_tasks: &ProjectIndex) {
+    let projects_by_url: HashMap<&str, &Project> = all_tasks.iter()
+        .map(|project| (project.url.as_str(), project))
+        .collect();
+    for known_project in known_tasks.iter_mut() {
+        let project = match all_tasks.project(known_project) {
+            Some(project) => project,
+            None if known_project.node_id.is_empty() => match projects_by_url.get(known_project.url.as_str()) {
+                Some(project) => project,
+                None => continue,
+            },
+            None => continue,
+        };
+        known_project.node_id = project.node_id.clone();
+        let tasks_by_url: HashMap<&str, &Task> = project.tasks.iter()
+            .map(|task| (task.url.as_str(), task))
+            .collect();
+        for known_task in known_project.tasks.iter_mut() {
+            if let Some(task) = tasks_by_url.get(known_task.url.as_str()) {
+                known_task.node_id = task.node_id.clone();
+            }
+        }
     }
-
-    file.unlock().expect("failed to unlock persistence file!");
-    Ok(())
 }
 
-fn lookup_project<'a, 'b>(tasks: &'a mut Vec<Project>, subject: &'b Project) -> Option<&'a mut Project> {
-    for project in tasks.iter_mut() {
-        if project.url == subject.url {
-            return Some(project);
+/// Updates known projects that have been renamed or transferred, so they are only reported once.
+fn apply_renames(known_tasks: &mut ProjectIndex, all_tasks: &ProjectIndex) -> Vec<ProjectRename> {
+    let mut renames = Vec::new();
+    for known_project in known_tasks.iter_mut() {
+        let Some(project) = all_tasks.project(known_project) else {
+            continue;
+        };
+        if project.url == known_project.url {
+            continue;
+        }
+        renames.push(ProjectRename {
+            previous: format!("{}/{}", known_project.owner, known_project.name),
+            current: format!("{}/{}", project.owner, project.name),
+            url: project.url.clone(),
+        });
+        known_project.owner = project.owner.clone();
+        known_project.name = project.name.clone();
+        known_project.url = project.url.clone();
+        for known_task in known_project.tasks.iter_mut() {
+            if let Some(task) = all_tasks.task(project, known_task) {
+                known_task.url = task.url.clone();
+            }
         }
     }
-    None
+    renames
 }
 
-fn lookup_task<'a, 'b>(project: &'a mut Project, subject: &'b Task) -> Option<&'a mut Task> {
-    for task in project.tasks.iter_mut() {
-        if task.url == subject.url {
-            return Some(task);
+async fn fetch_tasks(forges: &[Box<dyn Forge>], source: &TaskSource, since: Option<chrono::DateTime<chrono::Utc>>, known_tasks: &[Project]) -> Result<FetchResult, Box<dyn Error>> {
+    let mut result = FetchResult { projects: Vec::new(), complete: true, unchanged: Vec::new() };
+    for forge in forges {
+        match source {
+            TaskSource::Poll => {
+                let fetched = forge.fetch_projects(since, known_tasks).await?;
+                result.projects.extend(fetched.projects);
+                result.complete &= fetched.complete;
+                result.unchanged.extend(fetched.unchanged);
+            },
+            TaskSource::Webhook(node_ids) => {
+                // webhooks only yield new tasks, so known tasks missing from their result are not necessarily closed
+                result.projects.extend(forge.fetch_projects_by_node_ids(node_ids).await?);
+                result.complete = false;
+            },
         }
     }
-    None
+    Ok(result)
 }
 
-fn upsert_task<'a>(tasks: &'a mut Vec<Project>, project: &Project, task: &Task) -> bool {
-    let project = match lookup_project(tasks, project) {
-        Some(project) => project,
-        None => {
-            let mut project_clone = (*project).clone();
-            project_clone.tasks.retain(|t| t.url == task.url);
-            tasks.push(project_clone);
-            return true;
+fn check_tasks_against_persistence(fetched: FetchResult, now: chrono::DateTime<chrono::Utc>, policy: &NotificationPolicy, known_tasks: &[Project], mutes: &[Mute]) -> ResultingTasks {
+    let FetchResult { projects: mut all_tasks, complete, unchanged } = fetched;
+
+    // skipped projects did not change, so their known tasks are still open
+    let unchanged: HashSet<String> = unchanged.into_iter().collect();
+    for known_project in known_tasks.iter().filter(|p| unchanged.contains(&p.node_id)) {
+        let mut project = known_project.clone();
+        project.tasks.retain(|t| t.missing_since.is_none());
+        all_tasks.push(project);
+    }
+
+    // muted tasks are neither recorded nor reported, so they are reported once unmuted, unless they were known before
+    let mutes: Vec<&Mute> = mutes.iter().filter(|mute| mute.is_active(now)).collect();
+    for project in all_tasks.iter_mut() {
+        if mutes.iter().any(|mute| mute.covers_project(project)) {
+            project.tasks.clear();
         }
-    };
-    if lookup_task(project, task).is_none() {
-        project.tasks.push(task.clone());
-        return true;
+        project.tasks.retain(|task| !mutes.iter().any(|mute| mute.covers_task(task)));
     }
-    false
-}
+    let all_tasks = ProjectIndex::new(all_tasks);
 
-async fn check_tasks_against_persistence(github_context: &GithubClientContext, retain_for: Duration, known_tasks: &Vec<Project>) -> Result<ResultingTasks, Box<dyn Error>> {
-    let now = chrono::Utc::now();
-    let mut known_tasks = known_tasks.clone();
-    let mut all_tasks = github::fetch_all_projects(&github_context).await?;
-    let mut notify_tasks: Vec<Project> = Vec::new();
-
-    known_tasks.retain_mut(|known_project| {
-        match lookup_project(&mut all_tasks, known_project) {
-            Some(project) => {
-                known_project.tasks.retain(|t| t.observed_at > now - retain_for && lookup_task(project, t).is_some());
-                !project.tasks.is_empty()
-            },
-            None => false,
+    let mut known_tasks = known_tasks.to_vec();
+    migrate_node_ids(&mut known_tasks, &all_tasks);
+    let mut known_tasks = ProjectIndex::new(known_tasks);
+    let renamed = apply_renames(&mut known_tasks, &all_tasks);
+
+    // known tasks are kept while open, so they are only reported once, and for a while after they have been closed
+    let closed_retention = policy.closed_retention;
+    known_tasks.retain(|known_project| {
+        let project = all_tasks.project(known_project);
+        let project_muted = mutes.iter().any(|mute| mute.covers_project(known_project));
+        for known_task in known_project.tasks.iter_mut() {
+            // muted known tasks are kept as they are, as they are not fetched
+            if project_muted || mutes.iter().any(|mute| mute.covers_task(known_task)) {
+                continue;
+            }
+            if project.is_some_and(|p| all_tasks.task(p, known_task).is_some()) {
+                known_task.missing_since = None;
+            } else if complete {
+                known_task.missing_since.get_or_insert(now);
+            }
         }
+        known_project.tasks.retain(|t| t.missing_since.is_none_or(|missing_since| missing_since > now - closed_retention));
+        let tracked = match complete {
+            true => project.is_some_and(|p| p.activity_watermark.is_some() || policy.baseline.is_some()),
+            false => known_project.activity_watermark.is_some() || known_project.first_seen_at.is_some(),
+        };
+        !known_project.tasks.is_empty() || tracked
     });
 
+    // in baseline mode every project is remembered from its first sighting, even without any tasks, so the tasks
+    // that already existed by then can be recorded silently
+    let mut sighted_incompletely: HashSet<&str> = HashSet::new();
+    if policy.baseline.is_some() {
+        for project in all_tasks.iter() {
+            if known_tasks.project(project).is_none() {
+                known_tasks.push(Project { tasks: Vec::new(), first_seen_at: Some(now), ..project.clone() });
+                // incomplete fetches only yield new tasks, which do not belong to the baseline
+                if !complete {
+                    sighted_incompletely.insert(project.node_id.as_str());
+                }
+            }
+        }
+    }
+
+    let mut notify_tasks = ProjectIndex::default();
     for project in all_tasks.iter() {
+        let baseline_before = policy.baseline.as_ref().and_then(|baseline| {
+            let first_seen_at = known_tasks.project(project)
+                .filter(|p| !sighted_incompletely.contains(p.node_id.as_str()))
+                .and_then(|p| p.first_seen_at);
+            [baseline.cutoff, first_seen_at].into_iter().flatten().max()
+        });
         for task in project.tasks.iter() {
-            if upsert_task(&mut known_tasks, project, task) {
-                upsert_task(&mut notify_tasks, project, task);
+            let mut task = task.clone();
+            if baseline_before.is_some_and(|before| task.created_at < before) {
+                task.history.baselined = true;
+            } else {
+                task.history.first_notified_at = Some(now);
+            }
+            if known_tasks.upsert_task(project, &task) && !task.history.baselined {
+                notify_tasks.upsert_task(project, &task);
             }
         }
     }
 
+    // refresh the activity of fetched tasks and remind about all open known tasks still waiting for a response, as
+    // incomplete fetches only yield some of them
+    let notified: HashSet<&str> = notify_tasks.iter()
+        .flat_map(|project| project.tasks.iter().map(|task| task.node_id.as_str()))
+        .collect();
+    for known_project in known_tasks.iter_mut() {
+        let project = all_tasks.project(known_project);
+        let project_muted = mutes.iter().any(|mute| mute.covers_project(known_project));
+        for known_task in known_project.tasks.iter_mut() {
+            if let Some(task) = project.and_then(|p| all_tasks.task(p, known_task)) {
+                known_task.updated_at = task.updated_at;
+            }
+            if known_task.missing_since.is_some() || project_muted || mutes.iter().any(|mute| mute.covers_task(known_task)) {
+                continue;
+            }
+            if !notified.contains(known_task.node_id.as_str()) && policy.reminder_due(known_task, now) {
+                known_task.history.last_reminded_at = Some(now);
+                known_task.history.reminder_count += 1;
+            }
+        }
+    }
+    let remind_tasks: Vec<Project> = known_tasks.iter()
+        .flat_map(|project| {
+            let tasks: Vec<Task> = project.tasks.iter()
+                .filter(|task| task.history.last_reminded_at == Some(now))
+                .cloned()
+                .collect();
+            (!tasks.is_empty()).then(|| Project { tasks, ..project.clone() })
+        })
+        .collect();
+
+    // watermarks are kept even for projects without any tasks
+    for project in all_tasks.iter().filter(|p| p.activity_watermark.is_some()) {
+        match known_tasks.project_mut(project) {
+            Some(known_project) => known_project.activity_watermark = project.activity_watermark,
+            None => known_tasks.push(Project { tasks: Vec::new(), ..project.clone() }),
+        }
+    }
+
+    let mut notify_tasks = notify_tasks.into_projects();
     notify_tasks.sort_by_key(|project| {
-        Reverse(project.tasks.as_slice().into_iter().map(|i| i.created_at).max())
+        Reverse(project.tasks.iter().map(|i| i.created_at).max())
     });
 
-    Ok(ResultingTasks { new_known: known_tasks, notify: notify_tasks })
+    ResultingTasks { new_known: known_tasks.into_projects(), notify: notify_tasks, remind: remind_tasks, renamed }
 }
 
-fn notify_about_tasks(notify_tasks: &Vec<Project>, email_context: &mut EmailContext) -> Result<(), Box<dyn Error>> {
-    if !notify_tasks.is_empty() {
-        let mut email_body = String::new();
-        for project in notify_tasks {
-            email_body.push_str(format!("Project: {}/{} ({})\n", project.owner, project.name, project.url).as_str());
+fn notify_about_tasks(reports: &[IdentityReport], cycle: &Cycle, notifier: &mut Notifier) -> Result<(), Box<dyn Error>> {
+    let reports: Vec<&IdentityReport> = reports.iter()
+        .filter(|report| !report.notify.is_empty() || !report.remind.is_empty() || !report.renamed.is_empty())
+        .collect();
+    if !reports.is_empty() {
+        let notification = notifier.templates.render(&reports, cycle)?;
 
-            for task in project.tasks.as_slice() {
-                let prefix = match &task.task_type {
-                    TaskType::Issue      => "Issue:       ",
-                    TaskType::Pr         => "Pull Request:",
-                    TaskType::Discussion => "Discussion:  ",
-                };
-                email_body.push_str(format!("  {} #{} {} by @{} ({}) -> {}\n", prefix, task.id, task.title, task.author, task.created_at, task.url).as_str());
-            }
-        }
-
-        println!("{}", email_body);
+        println!("{}", notification.text);
 
-        send_email(
-            email_context,
-            "GitHub: New Unsubscribed Tasks",
-            email_body.as_str(),
-        )?;
+        let delivery = send_email(
+            &mut notifier.email_context,
+            notification.subject.as_str(),
+            notification.text.as_str(),
+            notification.html.as_str(),
+        );
+        audit_delivery(&notifier.audit_log, &reports, notification.subject, &delivery);
+        delivery.result?;
     } else {
         println!("No new tasks to notify about!");
     }
     Ok(())
 }
 
+fn task_urls<'a>(projects: &'a [Project]) -> impl Iterator<Item = String> + 'a {
+    projects.iter().flat_map(|project| project.tasks.iter().map(|task| task.url.clone()))
+}
+
+fn audit_delivery(audit_log: &AuditLog, reports: &[&IdentityReport], subject: String, delivery: &Delivery) {
+    let (accepted, code, response) = match &delivery.result {
+        Ok(response) => (response.is_positive(), Some(response.code().to_string()), response.message().collect::<Vec<&str>>().join("\n")),
+        Err(err) => (false, err.status().map(|code| code.to_string()), err.to_string()),
+    };
+    let entry = AuditEntry {
+        attempted_at: chrono::Utc::now(),
+        channel: "email".to_string(),
+        subject,
+        recipients: delivery.recipients.clone(),
+        notified: reports.iter().flat_map(|report| task_urls(report.notify)).collect(),
+        reminded: reports.iter().flat_map(|report| task_urls(report.remind)).collect(),
+        accepted,
+        code,
+        response,
+        message_id: delivery.message_id.clone(),
+    };
+    // the notification has been attempted either way, so failing the cycle would only risk sending it again
+    if let Err(err) = audit_log.append(&entry) {
+        eprintln!("Failed to append to the audit log: {}", err);
+    }
+}
+
 fn get_env(name: &str) -> String {
     match std::env::var(name) {
         Ok(value) => value,
@@ -227,35 +585,445 @@ fn bool_from_env(name: &str, default: bool) -> bool {
     }
 }
 
-fn duration_from_env(name: &str, default: Duration) -> Duration {
+fn list_from_env(name: &str) -> Vec<String> {
     match std::env::var(name) {
-        Ok(value) => duration(value.as_str()).expect(format!("{name} expects a ISO8601 duration!").as_str()).into(),
+        Ok(value) => value.split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn fetch_strategy_from_env(name: &str, default: FetchStrategy) -> FetchStrategy {
+    match std::env::var(name) {
+        Ok(value) => match value.to_lowercase().trim() {
+            "full" => FetchStrategy::FullScan,
+            "search" => FetchStrategy::Search,
+            other => panic!("{name} expects either full or search, got: {other}"),
+        },
         Err(_) => default,
     }
 }
 
+fn reaction_from_env(name: &str) -> Option<Reaction> {
+    let value = std::env::var(name).ok()?;
+    let reaction = match value.to_lowercase().trim() {
+        "+1" | "thumbs_up" => Reaction::ThumbsUp,
+        "-1" | "thumbs_down" => Reaction::ThumbsDown,
+        "laugh" => Reaction::Laugh,
+        "hooray" => Reaction::Hooray,
+        "confused" => Reaction::Confused,
+        "heart" => Reaction::Heart,
+        "rocket" => Reaction::Rocket,
+        "eyes" => Reaction::Eyes,
+        other => panic!("{name} expects one of +1, -1, laugh, hooray, confused, heart, rocket or eyes, got: {other}"),
+    };
+    Some(reaction)
+}
+
+fn reminder_cadence_from_env(cadence_name: &str, interval_name: &str) -> ReminderCadence {
+    let interval = duration_from_env(interval_name, Duration::from_hours(24));
+    match std::env::var(cadence_name) {
+        Ok(value) => match value.to_lowercase().trim() {
+            "never" => ReminderCadence::Never,
+            "interval" => ReminderCadence::Interval(interval),
+            "backoff" => ReminderCadence::Backoff(interval),
+            other => panic!("{cadence_name} expects either never, interval or backoff, got: {other}"),
+        },
+        Err(_) => ReminderCadence::Never,
+    }
+}
+
+fn cassette_from_env(mode_name: &str, directory_name: &str, identity: Option<&str>) -> Option<Cassette> {
+    let mode = match std::env::var(mode_name) {
+        Ok(value) => match value.to_lowercase().trim() {
+            "record" => CassetteMode::Record,
+            "replay" => CassetteMode::Replay,
+            other => panic!("{mode_name} expects either record or replay, got: {other}"),
+        },
+        Err(_) => return None,
+    };
+    let mut directory = PathBuf::from(std::env::var(directory_name).unwrap_or("cassettes".to_string()));
+    if let Some(identity) = identity {
+        directory.push(identity);
+    }
+    Some(Cassette { mode, directory })
+}
+
+fn duration_from_env(name: &str, default: Duration) -> Duration {
+    optional_duration_from_env(name).unwrap_or(default)
+}
+
+fn optional_duration_from_env(name: &str) -> Option<Duration> {
+    std::env::var(name).ok()
+        .map(|value| duration(value.as_str()).unwrap_or_else(|_| panic!("{name} expects a ISO8601 duration!")).into())
+}
+
+/// Parses a RFC 3339 timestamp or a plain date, which is taken as midnight UTC.
+fn parse_datetime(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.to_utc());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+fn datetime_from_env(name: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = std::env::var(name).ok()?;
+    Some(parse_datetime(value.as_str()).unwrap_or_else(|| panic!("{name} expects a RFC 3339 timestamp or a date!")))
+}
+
+fn template_from_env(name: &str) -> Option<String> {
+    let path = std::env::var(name).ok()?;
+    Some(fs::read_to_string(path.as_str()).unwrap_or_else(|err| panic!("{name} points to an unreadable template {path}: {err}")))
+}
+
+fn persistence_path() -> String {
+    std::env::var("PERSISTENCE_FILE").unwrap_or("persistence.json".to_string())
+}
+
+fn database_path() -> Option<String> {
+    std::env::var("PERSISTENCE_DATABASE").ok()
+}
+
+fn repository_cache_path() -> String {
+    std::env::var("REPOSITORY_CACHE_FILE").unwrap_or("repositories.json".to_string())
+}
+
+fn identity_names() -> Vec<Option<String>> {
+    let names = list_from_env("GITHUB_IDENTITIES");
+    if names.is_empty() {
+        return vec![None];
+    }
+    names.into_iter().map(Some).collect()
+}
+
+/// Derives the path of a file belonging to an identity, e.g. persistence.work.json from persistence.json.
+fn identity_path(path: &str, identity: Option<&str>) -> String {
+    let Some(identity) = identity else {
+        return path.to_string();
+    };
+    let path = Path::new(path);
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(identity);
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+fn identity_secret(name: &str, identity: Option<&str>) -> String {
+    match identity {
+        Some(identity) => format!("github_{}_{}", identity, name),
+        None => format!("github_{}", name),
+    }
+}
+
+fn audit_log() -> AuditLog {
+    AuditLog { path: std::env::var("AUDIT_LOG_FILE").unwrap_or("audit.jsonl".to_string()).into() }
+}
+
+fn print_audit_entries(url: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let entries = audit_log().read()?;
+    let mut found = false;
+    for entry in entries.iter().filter(|entry| url.is_none_or(|url| entry.mentions(url))) {
+        found = true;
+        let status = if entry.accepted { "accepted" } else { "failed" };
+        println!("{} {} {} ({}) to {}: {}", entry.attempted_at, entry.channel, status, entry.code.as_deref().unwrap_or("no response"), entry.recipients.join(", "), entry.subject);
+        if let Some(message_id) = &entry.message_id {
+            println!("  Message-ID: {}", message_id);
+        }
+        if !entry.accepted {
+            println!("  Response: {}", entry.response);
+        }
+        for (kind, task_urls) in [("Notified", &entry.notified), ("Reminded", &entry.reminded)] {
+            for task_url in task_urls.iter().filter(|task_url| url.is_none_or(|url| task_url.contains(url))) {
+                println!("  {}: {}", kind, task_url);
+            }
+        }
+    }
+    if !found {
+        match url {
+            Some(url) => println!("No delivery attempts mention {}.", url),
+            None => println!("No delivery attempts have been recorded."),
+        }
+    }
+    Ok(())
+}
+
+fn lock_identity_state(identity: Option<&str>) -> Result<Box<dyn Persistence>, Box<dyn Error>> {
+    let database_path = database_path().map(|path| identity_path(path.as_str(), identity));
+    persistence::lock(identity_path(persistence_path().as_str(), identity).as_str(), database_path.as_deref())
+}
+
+fn read_identity_state(identity: Option<&str>) -> Result<State, Box<dyn Error>> {
+    let database_path = database_path().map(|path| identity_path(path.as_str(), identity));
+    persistence::read_shared(identity_path(persistence_path().as_str(), identity).as_str(), database_path.as_deref())
+}
+
+/// Applies a change to the mutes of every identity, waiting for a running cycle to finish first.
+fn update_mutes(update: impl Fn(&mut Vec<Mute>)) -> Result<(), Box<dyn Error>> {
+    for identity in identity_names() {
+        let identity = identity.as_deref();
+        let persistence = lock_identity_state(identity)?;
+        let state = persistence.read()?;
+        let mut mutes = state.mutes.clone();
+        update(&mut mutes);
+        if mutes != state.mutes {
+            let lifted: Vec<Mute> = state.mutes.iter()
+                .filter(|previous| !mutes.iter().any(|mute| mute.same_target(&previous.target)))
+                .cloned()
+                .collect();
+            let mut projects = state.projects;
+            forget_watermarks(&mut projects, &lifted);
+            persistence.write(&State::new(projects, state.last_successful_cycle, state.last_complete_cycle, mutes))?;
+        }
+        persistence.unlock()?;
+    }
+    Ok(())
+}
+
+fn print_mutes() -> Result<(), Box<dyn Error>> {
+    let now = chrono::Utc::now();
+    let mut found = false;
+    for identity in identity_names() {
+        let identity = identity.as_deref();
+        let state = read_identity_state(identity)?;
+        for mute in state.mutes.iter().filter(|mute| mute.is_active(now)) {
+            found = true;
+            let prefix = identity.map(|name| format!("[{}] ", name)).unwrap_or_default();
+            match mute.until {
+                Some(until) => println!("{}{} snoozed until {}", prefix, mute.target, until),
+                None => println!("{}{} muted since {}", prefix, mute.target, mute.muted_at),
+            }
+        }
+    }
+    if !found {
+        println!("Nothing is muted.");
+    }
+    Ok(())
+}
+
+/// Tasks that appeared while a project was muted did not count as activity, so projects are fetched in full again
+/// once the mute is lifted.
+fn forget_watermarks(projects: &mut [Project], lifted: &[Mute]) {
+    for project in projects.iter_mut().filter(|project| lifted.iter().any(|mute| mute.concerns_project(project))) {
+        project.activity_watermark = None;
+    }
+}
+
+fn mute_target(args: &[String]) -> Result<String, Box<dyn Error>> {
+    let target = args.first().ok_or("expected the URL of a task, or the URL or owner/name of a repository")?;
+    Ok(target.trim().trim_end_matches('/').to_lowercase())
+}
+
+fn mute(target: String, until: Option<chrono::DateTime<chrono::Utc>>) -> Result<(), Box<dyn Error>> {
+    let muted_at = chrono::Utc::now();
+    update_mutes(|mutes| {
+        mutes.retain(|mute| !mute.same_target(&target));
+        mutes.push(Mute { target: target.clone(), muted_at, until });
+    })
+}
+
+/// Writes the known tasks of all identities to stdout, e.g.
+/// `export --format csv --owner someone --type pr --older-than P30D`.
+fn export_known_tasks(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let now = chrono::Utc::now();
+    let mut format = ExportFormat::Csv;
+    let mut filter = ExportFilter::default();
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", option))?;
+        let age = || duration(value.as_str())
+            .map(|age| now - Duration::from(age))
+            .map_err(|_| format!("{} expects a ISO8601 duration", option));
+        match option.as_str() {
+            "--format" => format = ExportFormat::from_name(value).ok_or_else(|| format!("unknown format: {}", value))?,
+            "--owner" => filter.owner = Some(value.clone()),
+            "--repository" => filter.repository = Some(value.clone()),
+            "--type" => filter.task_type = Some(serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+                .map_err(|_| format!("unknown task type: {}", value))?),
+            "--author" => filter.author = Some(value.trim_start_matches('@').to_string()),
+            "--older-than" => filter.created_before = Some(age()?),
+            "--newer-than" => filter.created_after = Some(age()?),
+            _ => return Err(format!("unknown option: {}", option).into()),
+        }
+    }
+
+    let mut states = Vec::new();
+    for identity in identity_names() {
+        let state = read_identity_state(identity.as_deref())?;
+        states.push((identity, state));
+    }
+    let rows = states.iter()
+        .flat_map(|(identity, state)| export_rows(identity.as_deref(), &state.projects, &filter));
+    let mut out = std::io::stdout().lock();
+    write_export(&mut out, &format, rows)
+}
+
+fn run_command(command: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+    match command {
+        "audit" => print_audit_entries(args.first().map(String::as_str))?,
+        "export" => export_known_tasks(args)?,
+        "mutes" => print_mutes()?,
+        "mute" => {
+            let target = mute_target(args)?;
+            mute(target.clone(), None)?;
+            println!("Muted {} until it is unmuted.", target);
+        },
+        "snooze" => {
+            let target = mute_target(args)?;
+            let until = args.get(1).ok_or("expected an ISO8601 duration, a RFC 3339 timestamp or a date")?;
+            let until = match duration(until.as_str()) {
+                Ok(snooze) => chrono::Utc::now() + Duration::from(snooze),
+                Err(_) => parse_datetime(until.as_str()).ok_or_else(|| format!("invalid duration or date: {}", until))?,
+            };
+            if until <= chrono::Utc::now() {
+                return Err(format!("snooze must end in the future, not at {}", until).into());
+            }
+            mute(target.clone(), Some(until))?;
+            println!("Snoozed {} until {}.", target, until);
+        },
+        "unmute" => {
+            let target = mute_target(args)?;
+            update_mutes(|mutes| mutes.retain(|mute| !mute.same_target(&target)))?;
+            println!("Unmuted {}.", target);
+        },
+        "refresh-repositories" => {
+            for identity in identity_names() {
+                match fs::remove_file(identity_path(repository_cache_path().as_str(), identity.as_deref())) {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(Box::new(err)),
+                    _ => {},
+                }
+            }
+            println!("Repositories will be discovered again during the next cycle.");
+        },
+        _ => return Err(format!("unknown command: {}", command).into()),
+    }
+    Ok(())
+}
+
+/// Creates the identities, along with the flags that make each of them discover its repositories again.
+fn create_identities(network_config: &NetworkConfig, force_repository_refresh: &mut Vec<Arc<AtomicBool>>) -> Vec<Identity> {
+    let persistence_path = persistence_path();
+    let database_path = database_path();
+
+    // a scripted forge replaces all remote ones, to run cycles offline
+    if let Ok(script_path) = std::env::var("FORGE_SCRIPT") {
+        let forge = InMemoryForge::from_file(script_path.as_str())
+            .unwrap_or_else(|err| panic!("failed to load forge script {script_path}: {err}"));
+        return vec![Identity { name: None, forges: vec![Box::new(forge)], persistence_path, database_path }];
+    }
+
+    let client = create_http_client(network_config).expect("failed to setup http client");
+    let mut identities: Vec<Identity> = identity_names().into_iter()
+        .map(|name| {
+            let identity = name.as_deref();
+            let github_context = GithubClientContext {
+                client: client.clone(),
+                username: read_required_secret(identity_secret("username", identity).as_str()),
+                access_token: read_required_secret(identity_secret("access_token", identity).as_str()),
+                fetch_strategy: fetch_strategy_from_env("FETCH_STRATEGY", FetchStrategy::FullScan),
+                cassette: cassette_from_env("GITHUB_CASSETTE_MODE", "GITHUB_CASSETTE_DIR", identity),
+                repository_cache: std::env::var("REPOSITORY_REFRESH_INTERVAL").ok().map(|_| RepositoryCache {
+                    path: identity_path(repository_cache_path().as_str(), identity).into(),
+                    refresh_interval: duration_from_env("REPOSITORY_REFRESH_INTERVAL", Duration::from_hours(24)),
+                    force_refresh: {
+                        let force_refresh = Arc::new(AtomicBool::new(false));
+                        force_repository_refresh.push(force_refresh.clone());
+                        force_refresh
+                    },
+                }),
+                skip_unchanged: bool_from_env("SKIP_UNCHANGED_REPOSITORIES", false),
+                maintainers: Maintainers {
+                    logins: list_from_env("MAINTAINERS"),
+                    team: std::env::var("MAINTAINER_TEAM").ok(),
+                },
+                acknowledgement: reaction_from_env("ACKNOWLEDGEMENT_REACTION"),
+            };
+            Identity {
+                persistence_path: identity_path(persistence_path.as_str(), identity),
+                database_path: database_path.as_deref().map(|path| identity_path(path, identity)),
+                forges: vec![Box::new(github_context)],
+                name,
+            }
+        })
+        .collect();
+
+    // the other forges are checked along with the GitHub identity named by GITLAB_IDENTITY or GITEA_IDENTITY, and
+    // along with the first one by default
+    if let Some(access_token) = read_secret("gitlab_access_token") {
+        forge_identity(&mut identities, "GITLAB_IDENTITY").forges.push(Box::new(GitlabClientContext {
+            client: client.clone(),
+            base_url: std::env::var("GITLAB_URL").unwrap_or("https://gitlab.com".to_string()),
+            username: read_required_secret("gitlab_username"),
+            access_token,
+            groups: list_from_env("GITLAB_GROUPS"),
+        }));
+    }
+
+    if let Ok(base_url) = std::env::var("GITEA_URL") {
+        forge_identity(&mut identities, "GITEA_IDENTITY").forges.push(Box::new(GiteaClientContext {
+            client: client.clone(),
+            base_url,
+            username: read_required_secret("gitea_username"),
+            access_token: read_required_secret("gitea_access_token"),
+        }));
+    }
+
+    identities
+}
+
+fn forge_identity<'a>(identities: &'a mut [Identity], name: &str) -> &'a mut Identity {
+    let Ok(identity) = std::env::var(name) else {
+        return &mut identities[0];
+    };
+    identities.iter_mut()
+        .find(|candidate| candidate.name.as_deref() == Some(identity.as_str()))
+        .unwrap_or_else(|| panic!("{name} names the unknown identity {identity}!"))
+}
+
+fn build_hash() -> Option<&'static str> {
+    option_env!("BUILD_HASH").filter(|hash| !hash.is_empty() && *hash != "unknown")
+}
+
 #[tokio::main]
 async fn main() {
-    let build_hash = option_env!("BUILD_HASH");
-    if let Some(hash) = build_hash {
-        if hash != "" && hash != "unknown" {
-            println!("Built from: https://github.com/pschichtel/ProjectMonitor/commit/{}", hash);
-        }
+    if let Some(hash) = build_hash() {
+        println!("Built from: https://github.com/pschichtel/ProjectMonitor/commit/{}", hash);
     }
 
-    let github_username = read_required_secret("github_username");
-    let github_access_token = read_required_secret("github_access_token");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some((command, args)) = args.split_first() {
+        if let Err(err) = run_command(command.as_str(), args) {
+            println!("Failed to run {}: {}", command, err);
+            exit(1);
+        }
+        return;
+    }
 
-    let client = reqwest::Client::new();
-    let github_context = GithubClientContext {
-        client,
-        username: github_username.to_string(),
-        access_token: github_access_token.to_string()
+    let mut force_repository_refresh = Vec::new();
+    let network_config = NetworkConfig {
+        proxy: std::env::var("PROXY_URL").ok().map(|url| ProxyConfig {
+            url,
+            username: read_secret("proxy_username"),
+            password: read_secret("proxy_password"),
+            no_proxy: std::env::var("NO_PROXY").ok(),
+        }),
+        ca_bundle: std::env::var("CA_BUNDLE_FILE").ok().map(|path| {
+            fs::read(&path).unwrap_or_else(|err| panic!("failed to read CA bundle {path}: {err}"))
+        }),
     };
+    let identities = create_identities(&network_config, &mut force_repository_refresh);
+    let merge_notifications = bool_from_env("MERGE_IDENTITY_NOTIFICATIONS", true);
 
     let smtp_host = get_env("SMTP_HOST");
     let smtp_port = std::env::var("SMTP_PORT")
-        .map(|port| u16::from_str_radix(port.as_str(), 10).unwrap_or(SUBMISSION_PORT))
+        .map(|port| port.parse::<u16>().unwrap_or(SUBMISSION_PORT))
         .unwrap_or(SUBMISSION_PORT);
     let smtp_username = read_secret("smtp_username");
     let smtp_password = read_secret("smtp_password");
@@ -266,32 +1034,81 @@ async fn main() {
     };
     let email_from = email_address_from_env("EMAIL_FROM");
     let email_to = email_address_from_env("EMAIL_TO");
-    let mut email_context = create_email_client(smtp_host.as_str(), smtp_port, smtp_username, smtp_password, smtp_security, email_from, email_to)
+    let email_context = create_email_client(smtp_host.as_str(), smtp_port, smtp_username, smtp_password, smtp_security, network_config.ca_bundle.as_deref(), email_from, email_to)
         .expect("failed to setup email client");
+    let templates = Templates::new(
+        template_from_env("SUBJECT_TEMPLATE_FILE"),
+        template_from_env("TEXT_TEMPLATE_FILE"),
+        template_from_env("HTML_TEMPLATE_FILE"),
+    ).unwrap_or_else(|err| panic!("invalid notification template: {err}"));
+    let mut notifier = Notifier { email_context, templates, audit_log: audit_log() };
 
-    let persistence_path = std::env::var("PERSISTENCE_FILE")
-        .unwrap_or("persistence.json".to_string());
-
-    let delay = duration_from_env("DELAY", Duration::from_mins(15));
-    let task_retention = duration_from_env("TASK_RETENTION", Duration::from_hours(24));
-
-    task::spawn(async {
+    let webhook_config = std::env::var("WEBHOOK_LISTEN").ok().map(|listen| WebhookConfig {
+        listen: listen.parse().unwrap_or_else(|_| panic!("WEBHOOK_LISTEN expects a socket address!")),
+        secret: read_required_secret("github_webhook_secret").trim().to_string(),
+    });
+    // with webhooks in place, polling is only a safety net for missed deliveries
+    let delay = match webhook_config {
+        Some(_) => duration_from_env("RECONCILIATION_DELAY", Duration::from_hours(6)),
+        None => duration_from_env("DELAY", Duration::from_mins(15)),
+    };
+    let policy = NotificationPolicy {
+        // TASK_RETENTION is the former name, from when open tasks were forgotten and reported again as well
+        closed_retention: optional_duration_from_env("CLOSED_TASK_RETENTION")
+            .or_else(|| optional_duration_from_env("TASK_RETENTION"))
+            .unwrap_or(Duration::from_hours(24)),
+        stale_after: StaleThresholds {
+            issue: optional_duration_from_env("STALE_ISSUE_AFTER"),
+            pr: optional_duration_from_env("STALE_PR_AFTER"),
+            discussion: optional_duration_from_env("STALE_DISCUSSION_AFTER"),
+        },
+        reminder_cadence: reminder_cadence_from_env("REMINDER_CADENCE", "REMINDER_INTERVAL"),
+        max_reminders: std::env::var("MAX_REMINDERS").ok()
+            .map(|value| value.trim().parse().unwrap_or_else(|_| panic!("MAX_REMINDERS expects a number!"))),
+        baseline: match (bool_from_env("BASELINE", false), datetime_from_env("BASELINE_CUTOFF")) {
+            (false, None) => None,
+            (_, cutoff) => Some(Baseline { cutoff }),
+        },
+        full_scan_interval: duration_from_env("FULL_SCAN_INTERVAL", Duration::from_hours(24)),
+    };
+    task::spawn(async move {
         let mut sigint = signal(SignalKind::interrupt()).unwrap();
         let mut sigterm = signal(SignalKind::terminate()).unwrap();
+        let mut sighup = signal(SignalKind::hangup()).unwrap();
 
-        select! {
-            _ = sigint.recv() => {
-                println!("Received SIGINT, exiting cleanly...");
-            },
-            _ = sigterm.recv() => {
-                println!("Received SIGTERM, exiting cleanly...");
-            },
+        loop {
+            select! {
+                _ = sigint.recv() => {
+                    println!("Received SIGINT, exiting cleanly...");
+                    break;
+                },
+                _ = sigterm.recv() => {
+                    println!("Received SIGTERM, exiting cleanly...");
+                    break;
+                },
+                _ = sighup.recv() => {
+                    println!("Received SIGHUP, repositories will be discovered again during the next cycle...");
+                    for force_refresh in force_repository_refresh.iter() {
+                        force_refresh.store(true, Ordering::Relaxed);
+                    }
+                },
+            }
         }
         exit(0);
     });
 
+    let (webhook_sender, mut webhook_receiver) = unbounded_channel::<Vec<String>>();
+    if let Some(config) = webhook_config {
+        task::spawn(async move {
+            if let Err(err) = webhook::serve(config, webhook_sender).await {
+                println!("Webhook receiver failed: {}", err);
+                exit(1);
+            }
+        });
+    }
+
     loop {
-        match find_issues_for_notification(&github_context, task_retention, &mut email_context, persistence_path.as_str()).await {
+        match find_issues_for_notification(&identities, merge_notifications, &TaskSource::Poll, &policy, &mut notifier).await {
             Ok(_) => {
                 println!("Waiting {delay:?} for next check...")
             }
@@ -299,6 +1116,184 @@ async fn main() {
                 println!("Failed to check for new tasks: {}", err);
             }
         };
-        tokio::time::sleep(delay).await;
+
+        let next_cycle = tokio::time::sleep(delay);
+        tokio::pin!(next_cycle);
+        loop {
+            select! {
+                _ = &mut next_cycle => break,
+                Some(mut node_ids) = webhook_receiver.recv() => {
+                    while let Ok(more) = webhook_receiver.try_recv() {
+                        node_ids.extend(more);
+                    }
+                    let source = TaskSource::Webhook(node_ids);
+                    if let Err(err) = find_issues_for_notification(&identities, merge_notifications, &source, &policy, &mut notifier).await {
+                        println!("Failed to process webhook deliveries: {}", err);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::NotificationHistory;
+    use futures::executor::block_on;
+
+    fn start() -> chrono::DateTime<chrono::Utc> {
+        "2026-01-01T00:00:00Z".parse().unwrap()
+    }
+
+    fn task(number: i64, created_at: chrono::DateTime<chrono::Utc>) -> Task {
+        Task {
+            node_id: format!("I{}", number),
+            observed_at: created_at,
+            task_type: TaskType::Issue,
+            id: number,
+            title: format!("Issue {}", number),
+            created_at,
+            url: format!("https://example.com/me/repo/issues/{}", number),
+            author: "someone".to_string(),
+            updated_at: Some(created_at),
+            history: NotificationHistory::default(),
+            missing_since: None,
+        }
+    }
+
+    fn project(name: &str, tasks: Vec<Task>) -> Project {
+        Project {
+            node_id: "R1".to_string(),
+            name: name.to_string(),
+            owner: "me".to_string(),
+            url: format!("https://example.com/me/{}", name),
+            tasks,
+            activity_watermark: None,
+            first_seen_at: None,
+        }
+    }
+
+    fn policy() -> NotificationPolicy {
+        NotificationPolicy {
+            closed_retention: Duration::from_hours(24),
+            stale_after: StaleThresholds { issue: None, pr: None, discussion: None },
+            reminder_cadence: ReminderCadence::Never,
+            max_reminders: None,
+            baseline: None,
+            full_scan_interval: Duration::from_hours(24),
+        }
+    }
+
+    fn node_ids(projects: &[Project]) -> Vec<&str> {
+        projects.iter().flat_map(|project| project.tasks.iter().map(|task| task.node_id.as_str())).collect()
+    }
+
+    /// Runs cycles against the steps of a scripted forge, an hour apart.
+    struct Cycles {
+        forge: InMemoryForge,
+        known: Vec<Project>,
+        now: chrono::DateTime<chrono::Utc>,
+    }
+
+    impl Cycles {
+        fn new(steps: Vec<Vec<Project>>) -> Self {
+            Cycles { forge: InMemoryForge::new(steps), known: Vec::new(), now: start() }
+        }
+
+        fn run(&mut self, policy: &NotificationPolicy, mutes: &[Mute]) -> ResultingTasks {
+            let fetched = block_on(self.forge.fetch_projects(None, &self.known)).unwrap();
+            let result = check_tasks_against_persistence(fetched, self.now, policy, &self.known, mutes);
+            self.known = result.new_known.clone();
+            self.now += Duration::from_hours(1);
+            result
+        }
+    }
+
+    #[test]
+    fn new_task_is_notified_once() {
+        let mut cycles = Cycles::new(vec![vec![project("repo", vec![task(1, start())])]]);
+
+        assert_eq!(node_ids(&cycles.run(&policy(), &[]).notify), ["I1"]);
+        assert!(cycles.run(&policy(), &[]).notify.is_empty());
+        assert_eq!(node_ids(&cycles.known), ["I1"]);
+    }
+
+    #[test]
+    fn closed_task_is_retained_then_pruned() {
+        let policy = NotificationPolicy { closed_retention: Duration::from_hours(2), ..policy() };
+        let mut cycles = Cycles::new(vec![
+            vec![project("repo", vec![task(1, start())])],
+            vec![project("repo", Vec::new())],
+        ]);
+
+        cycles.run(&policy, &[]);
+        cycles.run(&policy, &[]);
+        assert_eq!(cycles.known[0].tasks[0].missing_since, Some(start() + Duration::from_hours(1)));
+        cycles.run(&policy, &[]);
+        assert_eq!(node_ids(&cycles.known), ["I1"]);
+        cycles.run(&policy, &[]);
+        assert!(cycles.known.is_empty());
+    }
+
+    #[test]
+    fn reminder_is_due_once_stale() {
+        let policy = NotificationPolicy {
+            stale_after: StaleThresholds { issue: Some(Duration::from_hours(2)), pr: None, discussion: None },
+            reminder_cadence: ReminderCadence::Interval(Duration::from_hours(24)),
+            ..policy()
+        };
+        // already stale when reported, which does not make the reminder due any earlier
+        let mut cycles = Cycles::new(vec![vec![project("repo", vec![task(1, start() - Duration::from_hours(10))])]]);
+
+        let first = cycles.run(&policy, &[]);
+        assert_eq!(node_ids(&first.notify), ["I1"]);
+        assert!(first.remind.is_empty());
+        assert!(cycles.run(&policy, &[]).remind.is_empty());
+        assert_eq!(node_ids(&cycles.run(&policy, &[]).remind), ["I1"]);
+        assert!(cycles.run(&policy, &[]).remind.is_empty());
+        assert_eq!(cycles.known[0].tasks[0].history.reminder_count, 1);
+    }
+
+    #[test]
+    fn rename_is_reported_once() {
+        let mut cycles = Cycles::new(vec![
+            vec![project("old", vec![task(1, start())])],
+            vec![project("new", vec![task(1, start())])],
+        ]);
+
+        cycles.run(&policy(), &[]);
+        let renamed = cycles.run(&policy(), &[]);
+        assert!(renamed.notify.is_empty());
+        let renames: Vec<(&str, &str)> = renamed.renamed.iter()
+            .map(|rename| (rename.previous.as_str(), rename.current.as_str()))
+            .collect();
+        assert_eq!(renames, [("me/old", "me/new")]);
+        assert!(cycles.run(&policy(), &[]).renamed.is_empty());
+        assert_eq!(cycles.known[0].url, "https://example.com/me/new");
+    }
+
+    #[test]
+    fn baselined_task_is_silent() {
+        let policy = NotificationPolicy { baseline: Some(Baseline { cutoff: None }), ..policy() };
+        let mut cycles = Cycles::new(vec![
+            vec![project("repo", vec![task(1, start() - Duration::from_hours(1))])],
+            vec![project("repo", vec![task(1, start() - Duration::from_hours(1)), task(2, start() + Duration::from_mins(30))])],
+        ]);
+
+        assert!(cycles.run(&policy, &[]).notify.is_empty());
+        assert!(cycles.known[0].tasks[0].history.baselined);
+        assert_eq!(node_ids(&cycles.run(&policy, &[]).notify), ["I2"]);
+        assert!(cycles.run(&policy, &[]).notify.is_empty());
+    }
+
+    #[test]
+    fn muted_task_is_hidden() {
+        let mut cycles = Cycles::new(vec![vec![project("repo", vec![task(1, start()), task(2, start())])]]);
+        let mute = |target: &str| Mute { target: target.to_string(), muted_at: start(), until: None };
+
+        assert_eq!(node_ids(&cycles.run(&policy(), &[mute("https://example.com/me/repo/issues/1")]).notify), ["I2"]);
+        assert!(cycles.run(&policy(), &[mute("ME/Repo")]).notify.is_empty());
+        assert_eq!(node_ids(&cycles.run(&policy(), &[]).notify), ["I1"]);
     }
 }
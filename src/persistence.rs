@@ -0,0 +1,322 @@
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use crate::build_hash;
+use crate::forge::{DateTime, Project, Task};
+use crate::sqlite::SqlitePersistence;
+
+/// Version of the layout written by this build. Older layouts are upgraded on load by [MIGRATIONS].
+pub const FORMAT_VERSION: u64 = 2;
+
+type Migration = fn(Value) -> Result<Value, Box<dyn Error>>;
+
+/// Migrations from every format version to the next one, starting with version 1.
+const MIGRATIONS: [Migration; 1] = [
+    migrate_bare_projects,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct State {
+    pub version: u64,
+    /// Build hash of the version that wrote the state, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub writer: Option<String>,
+    /// Start of the last cycle that completed successfully, updated by polling cycles only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_successful_cycle: Option<DateTime>,
+    /// Start of the last cycle that fetched all open tasks, which is how closed tasks are noticed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_complete_cycle: Option<DateTime>,
+    pub projects: Vec<Project>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mutes: Vec<Mute>,
+}
+
+impl State {
+    pub fn new(projects: Vec<Project>, last_successful_cycle: Option<DateTime>, last_complete_cycle: Option<DateTime>, mutes: Vec<Mute>) -> Self {
+        State {
+            version: FORMAT_VERSION,
+            writer: build_hash().map(str::to_string),
+            last_successful_cycle,
+            last_complete_cycle,
+            projects,
+            mutes,
+        }
+    }
+}
+
+/// Keeps a task or a whole repository out of all notifications, until removed or, for a snooze, until it expires.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Mute {
+    /// URL of a task, or URL or owner/name of a repository.
+    pub target: String,
+    pub muted_at: DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime>,
+}
+
+impl Mute {
+    pub fn is_active(&self, now: DateTime) -> bool {
+        self.until.is_none_or(|until| until > now)
+    }
+
+    /// Forges treat owners and names case-insensitively, and so do mutes.
+    pub fn covers_project(&self, project: &Project) -> bool {
+        self.target.eq_ignore_ascii_case(&project.url)
+            || self.target.eq_ignore_ascii_case(&format!("{}/{}", project.owner, project.name))
+    }
+
+    pub fn covers_task(&self, task: &Task) -> bool {
+        self.target.eq_ignore_ascii_case(&task.url)
+    }
+
+    /// Whether the mute holds back tasks of the project, as it covers the project itself or one of its tasks.
+    pub fn concerns_project(&self, project: &Project) -> bool {
+        self.covers_project(project)
+            || self.target.to_ascii_lowercase().starts_with(&format!("{}/", project.url.to_ascii_lowercase()))
+    }
+
+    pub fn same_target(&self, target: &str) -> bool {
+        self.target.eq_ignore_ascii_case(target)
+    }
+}
+
+/// The state of an identity, mainly its known tasks, locked for the duration of a cycle.
+pub trait Persistence {
+    fn read(&self) -> Result<State, Box<dyn Error>>;
+
+    fn write(&self, state: &State) -> Result<(), Box<dyn Error>>;
+
+    fn unlock(self: Box<Self>) -> Result<(), Box<dyn Error>>;
+}
+
+/// Locks the state stored in the given SQLite database, or in the given JSON file if no database is configured.
+/// A new database imports the state from the JSON file once.
+pub fn lock(path: &str, database_path: Option<&str>) -> Result<Box<dyn Persistence>, Box<dyn Error>> {
+    match database_path {
+        Some(database_path) => Ok(Box::new(SqlitePersistence::lock(database_path, path)?)),
+        None => Ok(Box::new(JsonPersistence::lock(path)?)),
+    }
+}
+
+/// Reads the state without keeping it locked, other readers are not blocked. A running cycle keeps the JSON file
+/// locked from start to end, so reading it waits for the whole cycle, while a database only blocks readers while a
+/// cycle commits.
+pub fn read_shared(path: &str, database_path: Option<&str>) -> Result<State, Box<dyn Error>> {
+    match database_path {
+        Some(database_path) => SqlitePersistence::read_shared(database_path, path),
+        None => JsonPersistence::read_shared(path),
+    }
+}
+
+/// Writes go to a temporary file which is synced and renamed over the current file, so the file is replaced atomically.
+/// The previous generation is kept as a backup, unless it is corrupt.
+/// As the file is replaced on every write, the lock is held on a separate lock file next to it.
+pub struct JsonPersistence {
+    path: PathBuf,
+    lock: File,
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Version 1 was a bare list of projects.
+fn migrate_bare_projects(state: Value) -> Result<Value, Box<dyn Error>> {
+    Ok(json!({ "version": 2, "projects": state }))
+}
+
+fn format_version(state: &Value) -> Result<u64, Box<dyn Error>> {
+    match state {
+        Value::Array(_) => Ok(1),
+        Value::Object(object) => object.get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| "missing format version".into()),
+        _ => Err("unexpected layout".into()),
+    }
+}
+
+fn migrate(mut state: Value) -> Result<State, Box<dyn Error>> {
+    let mut version = format_version(&state)?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(format!("unsupported format version {}, this build supports up to {}", version, FORMAT_VERSION).into());
+    }
+    while version < FORMAT_VERSION {
+        state = MIGRATIONS[(version - 1) as usize](state)?;
+        version = format_version(&state)?;
+    }
+    Ok(serde_json::from_value(state)?)
+}
+
+/// Reads the state from the given file, none if it does not exist or is empty.
+fn read_state(path: &Path) -> Result<Option<State>, Box<dyn Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(Box::new(err)),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    let state: Value = serde_json::from_reader(BufReader::new(file))?;
+    Ok(Some(migrate(state)?))
+}
+
+fn sync_directory(path: &Path) -> Result<(), Box<dyn Error>> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+impl JsonPersistence {
+    pub fn lock(path: &str) -> Result<Self, Box<dyn Error>> {
+        let persistence = Self::open(path)?;
+        persistence.lock.lock()?;
+        Ok(persistence)
+    }
+
+    /// Locks the state for reading only, it must not be written with this lock.
+    pub fn lock_shared(path: &str) -> Result<Self, Box<dyn Error>> {
+        let persistence = Self::open(path)?;
+        persistence.lock.lock_shared()?;
+        Ok(persistence)
+    }
+
+    pub fn read_shared(path: &str) -> Result<State, Box<dyn Error>> {
+        let persistence = Box::new(Self::lock_shared(path)?);
+        let state = persistence.read()?;
+        persistence.unlock()?;
+        Ok(state)
+    }
+
+    fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let lock = File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(sibling_path(&path, ".lock"))?;
+        Ok(JsonPersistence { path, lock })
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        sibling_path(&self.path, ".bak")
+    }
+}
+
+impl Persistence for JsonPersistence {
+    /// Reads the state, falling back to the backup if the file is corrupt or missing after an interrupted write.
+    /// Fails if neither can be read, as starting from scratch would report every open task again.
+    fn read(&self) -> Result<State, Box<dyn Error>> {
+        let current_error = match read_state(&self.path) {
+            Ok(Some(state)) => return Ok(state),
+            Ok(None) => None,
+            Err(err) => {
+                eprintln!("Failed to parse known tasks from {}: {}", self.path.display(), err);
+                Some(err)
+            },
+        };
+
+        let backup_path = self.backup_path();
+        match (read_state(&backup_path), current_error) {
+            (Ok(Some(state)), _) => {
+                eprintln!("Recovered known tasks from backup {}", backup_path.display());
+                Ok(state)
+            },
+            (Ok(None), None) => Ok(State::new(Vec::new(), None, None, Vec::new())),
+            (Ok(None), Some(err)) => Err(format!("known tasks are corrupt and there is no backup: {}", err).into()),
+            (Err(err), _) => Err(format!("failed to parse known tasks from backup {}: {}", backup_path.display(), err).into()),
+        }
+    }
+
+    fn write(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let temp_path = sibling_path(&self.path, ".tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer_pretty(&mut writer, state)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        // a corrupt file must not replace the backup the state has just been recovered from
+        if let Ok(Some(_)) = read_state(&self.path) {
+            let backup_temp_path = sibling_path(&self.path, ".bak.tmp");
+            match fs::remove_file(&backup_temp_path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(Box::new(err)),
+                _ => {},
+            }
+            if fs::hard_link(&self.path, &backup_temp_path).is_err() {
+                fs::copy(&self.path, &backup_temp_path)?;
+            }
+            fs::rename(&backup_temp_path, self.backup_path())?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        sync_directory(&self.path)
+    }
+
+    fn unlock(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.lock.unlock()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::{NotificationHistory, TaskType};
+
+    fn project() -> Project {
+        let created_at = "2026-01-01T00:00:00Z".parse().unwrap();
+        Project {
+            node_id: "R1".to_string(),
+            name: "repo".to_string(),
+            owner: "me".to_string(),
+            url: "https://example.com/me/repo".to_string(),
+            tasks: vec![Task {
+                node_id: "I1".to_string(),
+                observed_at: created_at,
+                task_type: TaskType::Issue,
+                id: 1,
+                title: "Bug".to_string(),
+                created_at,
+                url: "https://example.com/me/repo/issues/1".to_string(),
+                author: "someone".to_string(),
+                updated_at: None,
+                history: NotificationHistory::default(),
+                missing_since: None,
+            }],
+            activity_watermark: None,
+            first_seen_at: None,
+        }
+    }
+
+    #[test]
+    fn migrates_bare_projects() {
+        let path = std::env::temp_dir().join(format!("project-monitor-{}-bare.json", std::process::id()));
+        fs::write(&path, serde_json::to_vec(&vec![project()]).unwrap()).unwrap();
+
+        let state = read_state(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(state.version, FORMAT_VERSION);
+        assert_eq!(state.projects, vec![project()]);
+        assert!(state.mutes.is_empty());
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [0, FORMAT_VERSION + 1] {
+            let err = migrate(json!({ "version": version, "projects": [] })).unwrap_err();
+            assert!(err.to_string().contains("unsupported format version"), "{}", err);
+        }
+        assert!(migrate(json!({ "projects": [] })).is_err());
+        assert!(migrate(json!("projects")).is_err());
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use core::time::Duration;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde_json::Value;
+use crate::forge::{node_key, NotificationHistory, Project, Task};
+use crate::persistence::{JsonPersistence, Mute, Persistence, State, FORMAT_VERSION};
+
+/// Version of the schema, kept in the user_version pragma of the database. New databases are created with [SCHEMA]
+/// and then upgraded by [SCHEMA_MIGRATIONS], just like existing ones.
+const SCHEMA_VERSION: i64 = 4;
+
+/// Migrations from every schema version to the next one, starting with version 1.
+const SCHEMA_MIGRATIONS: [&str; 3] = [
+    "ALTER TABLE projects ADD COLUMN first_seen_at TEXT;
+     ALTER TABLE tasks ADD COLUMN baselined INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE mutes (
+         target TEXT PRIMARY KEY,
+         muted_at TEXT NOT NULL,
+         until TEXT
+     );",
+    "ALTER TABLE state ADD COLUMN last_complete_cycle TEXT;",
+];
+
+const SCHEMA: &str = "
+CREATE TABLE state (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    writer TEXT,
+    last_successful_cycle TEXT
+);
+
+CREATE TABLE projects (
+    key TEXT PRIMARY KEY,
+    node_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    url TEXT NOT NULL,
+    activity_watermark TEXT
+);
+
+CREATE TABLE tasks (
+    key TEXT PRIMARY KEY,
+    project_key TEXT NOT NULL REFERENCES projects (key) ON DELETE CASCADE,
+    node_id TEXT NOT NULL,
+    observed_at TEXT NOT NULL,
+    task_type TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    url TEXT NOT NULL,
+    author TEXT NOT NULL,
+    updated_at TEXT,
+    first_notified_at TEXT,
+    last_reminded_at TEXT,
+    reminder_count INTEGER NOT NULL DEFAULT 0,
+    missing_since TEXT
+);
+
+CREATE INDEX tasks_project_key ON tasks (project_key);
+";
+
+/// Keeps the state in a SQLite database. Every cycle runs in a single transaction, which also serves as the lock, and
+/// only rows that changed are written. Rows are keyed like [node_key].
+pub struct SqlitePersistence {
+    connection: Connection,
+}
+
+fn same_project(a: &Project, b: &Project) -> bool {
+    a.node_id == b.node_id
+        && a.name == b.name
+        && a.owner == b.owner
+        && a.url == b.url
+        && a.activity_watermark == b.activity_watermark
+        && a.first_seen_at == b.first_seen_at
+}
+
+impl SqlitePersistence {
+    pub fn lock(path: &str, json_path: &str) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        // wait for other processes holding the lock just as long as with a lock file
+        connection.busy_timeout(Duration::from_millis(i32::MAX as u64))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON; BEGIN IMMEDIATE;")?;
+        let persistence = SqlitePersistence { connection };
+        persistence.migrate_schema()?;
+
+        if !persistence.has_state()? && Path::new(json_path).exists() {
+            let json = Box::new(JsonPersistence::lock(json_path)?);
+            persistence.write(&json.read()?)?;
+            json.unlock()?;
+            println!("Imported known tasks from {} into {}", json_path, path);
+        }
+        Ok(persistence)
+    }
+
+    /// Reads the state in a read-only transaction, which does not block other readers. Until the database exists, the
+    /// state is read from the JSON file it is going to import. Databases that still need to be upgraded are locked like
+    /// for a cycle instead.
+    pub fn read_shared(path: &str, json_path: &str) -> Result<State, Box<dyn Error>> {
+        // creating the database would import the JSON file, which is left to the next cycle
+        if !Path::new(path).exists() {
+            return JsonPersistence::read_shared(json_path);
+        }
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        connection.busy_timeout(Duration::from_millis(i32::MAX as u64))?;
+        connection.execute_batch("BEGIN DEFERRED;")?;
+        let persistence = SqlitePersistence { connection };
+        if persistence.schema_version()? == SCHEMA_VERSION && persistence.has_state()? {
+            let state = persistence.read()?;
+            persistence.connection.execute_batch("COMMIT")?;
+            return Ok(state);
+        }
+        drop(persistence);
+
+        let persistence = Box::new(Self::lock(path, json_path)?);
+        let state = persistence.read()?;
+        persistence.unlock()?;
+        Ok(state)
+    }
+
+    fn schema_version(&self) -> Result<i64, Box<dyn Error>> {
+        Ok(self.connection.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    fn migrate_schema(&self) -> Result<(), Box<dyn Error>> {
+        let mut version = self.schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(format!("unsupported schema version {}, this build supports up to {}", version, SCHEMA_VERSION).into());
+        }
+        if version == 0 {
+            self.connection.execute_batch(SCHEMA)?;
+            version = 1;
+        }
+        while version < SCHEMA_VERSION {
+            self.connection.execute_batch(SCHEMA_MIGRATIONS[(version - 1) as usize])?;
+            version += 1;
+        }
+        self.connection.pragma_update(None, "user_version", version)?;
+        Ok(())
+    }
+
+    fn has_state(&self) -> Result<bool, Box<dyn Error>> {
+        let count: i64 = self.connection.query_row("SELECT COUNT(*) FROM state", [], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    fn read_projects(&self) -> Result<Vec<Project>, Box<dyn Error>> {
+        let mut projects: Vec<Project> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+
+        let mut statement = self.connection.prepare(
+            "SELECT key, node_id, name, owner, url, activity_watermark, first_seen_at FROM projects ORDER BY rowid"
+        )?;
+        let rows = statement.query_map([], |row| {
+            let project = Project {
+                node_id: row.get(1)?,
+                name: row.get(2)?,
+                owner: row.get(3)?,
+                url: row.get(4)?,
+                tasks: Vec::new(),
+                activity_watermark: row.get(5)?,
+                first_seen_at: row.get(6)?,
+            };
+            Ok((row.get::<_, String>(0)?, project))
+        })?;
+        for row in rows {
+            let (key, project) = row?;
+            positions.insert(key, projects.len());
+            projects.push(project);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT project_key, node_id, observed_at, task_type, id, title, created_at, url, author, updated_at, \
+             first_notified_at, last_reminded_at, reminder_count, baselined, missing_since FROM tasks ORDER BY rowid"
+        )?;
+        let rows = statement.query_map([], |row| {
+            let task_type = serde_json::from_value(Value::String(row.get(3)?))
+                .map_err(|err| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(err)))?;
+            let task = Task {
+                node_id: row.get(1)?,
+                observed_at: row.get(2)?,
+                task_type,
+                id: row.get(4)?,
+                title: row.get(5)?,
+                created_at: row.get(6)?,
+                url: row.get(7)?,
+                author: row.get(8)?,
+                updated_at: row.get(9)?,
+                history: NotificationHistory {
+                    first_notified_at: row.get(10)?,
+                    last_reminded_at: row.get(11)?,
+                    reminder_count: row.get(12)?,
+                    baselined: row.get(13)?,
+                },
+                missing_since: row.get(14)?,
+            };
+            Ok((row.get::<_, String>(0)?, task))
+        })?;
+        for row in rows {
+            let (project_key, task) = row?;
+            let position = positions.get(&project_key)
+                .ok_or_else(|| format!("task of unknown project {}", project_key))?;
+            projects[*position].tasks.push(task);
+        }
+        Ok(projects)
+    }
+
+    fn read_mutes(&self) -> Result<Vec<Mute>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare("SELECT target, muted_at, until FROM mutes ORDER BY rowid")?;
+        let rows = statement.query_map([], |row| Ok(Mute { target: row.get(0)?, muted_at: row.get(1)?, until: row.get(2)? }))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn upsert_project(&self, key: &str, project: &Project) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "INSERT INTO projects (key, node_id, name, owner, url, activity_watermark, first_seen_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) \
+             ON CONFLICT (key) DO UPDATE SET node_id = excluded.node_id, name = excluded.name, owner = excluded.owner, \
+             url = excluded.url, activity_watermark = excluded.activity_watermark, first_seen_at = excluded.first_seen_at",
+            params![key, project.node_id, project.name, project.owner, project.url, project.activity_watermark, project.first_seen_at],
+        )?;
+        Ok(())
+    }
+
+    fn upsert_task(&self, key: &str, project_key: &str, task: &Task) -> Result<(), Box<dyn Error>> {
+        let task_type = match serde_json::to_value(&task.task_type)? {
+            Value::String(name) => name,
+            other => return Err(format!("unexpected task type {}", other).into()),
+        };
+        self.connection.execute(
+            "INSERT INTO tasks (key, project_key, node_id, observed_at, task_type, id, title, created_at, url, author, \
+             updated_at, first_notified_at, last_reminded_at, reminder_count, baselined, missing_since) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16) \
+             ON CONFLICT (key) DO UPDATE SET project_key = excluded.project_key, node_id = excluded.node_id, \
+             observed_at = excluded.observed_at, task_type = excluded.task_type, id = excluded.id, title = excluded.title, \
+             created_at = excluded.created_at, url = excluded.url, author = excluded.author, updated_at = excluded.updated_at, \
+             first_notified_at = excluded.first_notified_at, last_reminded_at = excluded.last_reminded_at, \
+             reminder_count = excluded.reminder_count, baselined = excluded.baselined, missing_since = excluded.missing_since",
+            params![
+                key, project_key, task.node_id, task.observed_at, task_type, task.id, task.title, task.created_at,
+                task.url, task.author, task.updated_at, task.history.first_notified_at, task.history.last_reminded_at,
+                task.history.reminder_count, task.history.baselined, task.missing_since,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl Persistence for SqlitePersistence {
+    fn read(&self) -> Result<State, Box<dyn Error>> {
+        let stored = self.connection.query_row(
+            "SELECT writer, last_successful_cycle, last_complete_cycle FROM state WHERE id = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?;
+        let (writer, last_successful_cycle, last_complete_cycle) = stored.unwrap_or((None, None, None));
+        Ok(State {
+            version: FORMAT_VERSION,
+            writer,
+            last_successful_cycle,
+            last_complete_cycle,
+            projects: self.read_projects()?,
+            mutes: self.read_mutes()?,
+        })
+    }
+
+    fn write(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let previous = self.read_projects()?;
+        let previous_projects: HashMap<&str, &Project> = previous.iter()
+            .map(|project| (node_key(&project.node_id, &project.url), project))
+            .collect();
+        let previous_tasks: HashMap<&str, (&str, &Task)> = previous.iter()
+            .flat_map(|project| project.tasks.iter().map(move |task| {
+                (node_key(&task.node_id, &task.url), (node_key(&project.node_id, &project.url), task))
+            }))
+            .collect();
+
+        let mut project_keys: HashSet<&str> = HashSet::new();
+        let mut task_keys: HashSet<&str> = HashSet::new();
+        for project in state.projects.iter() {
+            let project_key = node_key(&project.node_id, &project.url);
+            project_keys.insert(project_key);
+            if !previous_projects.get(project_key).is_some_and(|previous| same_project(previous, project)) {
+                self.upsert_project(project_key, project)?;
+            }
+            for task in project.tasks.iter() {
+                let task_key = node_key(&task.node_id, &task.url);
+                task_keys.insert(task_key);
+                if previous_tasks.get(task_key) != Some(&(project_key, task)) {
+                    self.upsert_task(task_key, project_key, task)?;
+                }
+            }
+        }
+
+        for key in previous_tasks.keys().filter(|key| !task_keys.contains(*key)) {
+            self.connection.execute("DELETE FROM tasks WHERE key = ?1", params![key])?;
+        }
+        for key in previous_projects.keys().filter(|key| !project_keys.contains(*key)) {
+            self.connection.execute("DELETE FROM projects WHERE key = ?1", params![key])?;
+        }
+        // there are only a few mutes, so they are replaced as a whole
+        if self.read_mutes()? != state.mutes {
+            self.connection.execute("DELETE FROM mutes", [])?;
+            for mute in state.mutes.iter() {
+                self.connection.execute(
+                    "INSERT INTO mutes (target, muted_at, until) VALUES (?1, ?2, ?3)",
+                    params![mute.target, mute.muted_at, mute.until],
+                )?;
+            }
+        }
+        self.connection.execute(
+            "INSERT INTO state (id, writer, last_successful_cycle, last_complete_cycle) VALUES (0, ?1, ?2, ?3) \
+             ON CONFLICT (id) DO UPDATE SET writer = excluded.writer, last_successful_cycle = excluded.last_successful_cycle, \
+             last_complete_cycle = excluded.last_complete_cycle",
+            params![state.writer, state.last_successful_cycle, state.last_complete_cycle],
+        )?;
+        Ok(())
+    }
+
+    fn unlock(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.connection.execute_batch("COMMIT")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::forge::TaskType;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("project-monitor-{}-{}", std::process::id(), name))
+    }
+
+    fn remove(paths: &[&PathBuf]) {
+        for path in paths {
+            for suffix in ["", ".lock", ".bak"] {
+                let _ = fs::remove_file(format!("{}{}", path.display(), suffix));
+            }
+        }
+    }
+
+    fn task(project: &str, number: i64) -> Task {
+        let created_at = "2026-01-01T00:00:00Z".parse().unwrap();
+        Task {
+            node_id: format!("{}I{}", project, number),
+            observed_at: created_at,
+            task_type: TaskType::Pr,
+            id: number,
+            title: format!("Task {}", number),
+            created_at,
+            url: format!("https://example.com/me/{}/pull/{}", project, number),
+            author: "someone".to_string(),
+            updated_at: Some(created_at),
+            history: NotificationHistory { first_notified_at: Some(created_at), last_reminded_at: None, reminder_count: 2, baselined: false },
+            missing_since: None,
+        }
+    }
+
+    fn project(name: &str, tasks: Vec<Task>) -> Project {
+        Project {
+            node_id: name.to_string(),
+            name: name.to_string(),
+            owner: "me".to_string(),
+            url: format!("https://example.com/me/{}", name),
+            tasks,
+            activity_watermark: Some("2026-01-02T00:00:00Z".parse().unwrap()),
+            first_seen_at: None,
+        }
+    }
+
+    fn write(path: &str, json_path: &str, state: &State) {
+        let persistence = Box::new(SqlitePersistence::lock(path, json_path).unwrap());
+        persistence.write(state).unwrap();
+        persistence.unlock().unwrap();
+    }
+
+    fn read(path: &str, json_path: &str) -> State {
+        let persistence = Box::new(SqlitePersistence::lock(path, json_path).unwrap());
+        let state = persistence.read().unwrap();
+        persistence.unlock().unwrap();
+        state
+    }
+
+    fn assert_same(actual: &State, expected: &State) {
+        assert_eq!(actual.projects, expected.projects);
+        assert_eq!(actual.mutes, expected.mutes);
+        assert_eq!(actual.last_successful_cycle, expected.last_successful_cycle);
+        assert_eq!(actual.last_complete_cycle, expected.last_complete_cycle);
+    }
+
+    #[test]
+    fn round_trip_deletes_tasks_and_projects() {
+        let database = temp_path("round-trip.db");
+        let json = temp_path("round-trip.json");
+        let (path, json_path) = (database.to_str().unwrap(), json.to_str().unwrap());
+        let cycle = Some("2026-01-03T00:00:00Z".parse().unwrap());
+        let mute = Mute { target: "me/muted".to_string(), muted_at: "2026-01-01T00:00:00Z".parse().unwrap(), until: None };
+
+        let first = State::new(
+            vec![project("a", vec![task("a", 1), task("a", 2)]), project("b", vec![task("b", 3)])],
+            cycle,
+            None,
+            vec![mute],
+        );
+        write(path, json_path, &first);
+        assert_same(&read(path, json_path), &first);
+
+        let mut closed = task("a", 1);
+        closed.missing_since = cycle;
+        let second = State::new(vec![project("a", vec![closed])], cycle, cycle, Vec::new());
+        write(path, json_path, &second);
+        assert_same(&read(path, json_path), &second);
+        assert_same(&SqlitePersistence::read_shared(path, json_path).unwrap(), &second);
+        remove(&[&database, &json]);
+    }
+
+    #[test]
+    fn imports_the_json_state_once() {
+        let database = temp_path("import.db");
+        let json = temp_path("import.json");
+        let (path, json_path) = (database.to_str().unwrap(), json.to_str().unwrap());
+        let imported = State::new(vec![project("a", vec![task("a", 1)])], Some("2026-01-03T00:00:00Z".parse().unwrap()), None, Vec::new());
+        let json_persistence = Box::new(JsonPersistence::lock(json_path).unwrap());
+        json_persistence.write(&imported).unwrap();
+        json_persistence.unlock().unwrap();
+
+        // before the first cycle, shared reads see the JSON state without creating the database
+        assert_same(&SqlitePersistence::read_shared(path, json_path).unwrap(), &imported);
+        assert!(!database.exists());
+
+        assert_same(&read(path, json_path), &imported);
+        let json_persistence = Box::new(JsonPersistence::lock(json_path).unwrap());
+        json_persistence.write(&State::new(Vec::new(), None, None, Vec::new())).unwrap();
+        json_persistence.unlock().unwrap();
+        assert_same(&read(path, json_path), &imported);
+        remove(&[&database, &json]);
+    }
+}
@@ -0,0 +1,287 @@
+use std::error::Error;
+use minijinja::syntax::SyntaxConfig;
+use minijinja::value::{Serde, Value};
+use minijinja::{Environment, ErrorKind};
+use serde::Serialize;
+use crate::forge::{DateTime, NotificationHistory, Project, Task, TaskType};
+use crate::{IdentityReport, ProjectRename};
+
+const SUBJECT: &str = include_str!("templates/subject.txt");
+const TEXT: &str = include_str!("templates/body.txt");
+const HTML: &str = include_str!("templates/body.html");
+
+/// Templates of the notification, HTML is escaped in the HTML body only.
+pub struct Templates {
+    environment: Environment<'static>,
+    /// The built-in templates, if any of them has been overridden.
+    fallback: Option<Environment<'static>>,
+}
+
+pub struct Notification {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+#[derive(Serialize)]
+pub struct Cycle<'a> {
+    pub started_at: DateTime,
+    /// Either poll or webhook.
+    pub source: &'static str,
+    pub build: Option<&'a str>,
+}
+
+#[derive(Serialize, Default)]
+struct TaskCounts {
+    issue: usize,
+    pr: usize,
+    discussion: usize,
+    total: usize,
+}
+
+impl TaskCounts {
+    fn count(projects: &[Project]) -> Self {
+        let mut counts = TaskCounts::default();
+        for task in projects.iter().flat_map(|project| project.tasks.iter()) {
+            match task.task_type {
+                TaskType::Issue => counts.issue += 1,
+                TaskType::Pr => counts.pr += 1,
+                TaskType::Discussion => counts.discussion += 1,
+            }
+            counts.total += 1;
+        }
+        counts
+    }
+
+    fn add(&mut self, other: &TaskCounts) {
+        self.issue += other.issue;
+        self.pr += other.pr;
+        self.discussion += other.discussion;
+        self.total += other.total;
+    }
+}
+
+#[derive(Serialize, Default)]
+struct Counts {
+    notify: TaskCounts,
+    remind: TaskCounts,
+    renamed: usize,
+}
+
+#[derive(Serialize)]
+struct TaskContext<'a> {
+    #[serde(flatten)]
+    task: &'a Task,
+    type_label: &'static str,
+    /// Only known for GitHub, which serves avatars for any login.
+    avatar_url: Option<String>,
+    age_days: i64,
+    last_activity_at: DateTime,
+}
+
+#[derive(Serialize)]
+struct ProjectContext<'a> {
+    node_id: &'a str,
+    owner: &'a str,
+    name: &'a str,
+    full_name: String,
+    url: &'a str,
+    tasks: Vec<TaskContext<'a>>,
+}
+
+#[derive(Serialize)]
+struct ReportContext<'a> {
+    identity: Option<&'a str>,
+    notify: Vec<ProjectContext<'a>>,
+    remind: Vec<ProjectContext<'a>>,
+    renamed: &'a [ProjectRename],
+    counts: Counts,
+}
+
+/// Everything the templates have access to.
+#[derive(Serialize)]
+struct NotificationContext<'a> {
+    reports: Vec<ReportContext<'a>>,
+    /// Identity of the only report, none for the default identity or if there are several reports.
+    identity: Option<&'a str>,
+    counts: Counts,
+    cycle: &'a Cycle<'a>,
+}
+
+fn task_context(task: &Task, now: DateTime) -> TaskContext<'_> {
+    TaskContext {
+        task,
+        type_label: match task.task_type {
+            TaskType::Issue => "Issue",
+            TaskType::Pr => "Pull Request",
+            TaskType::Discussion => "Discussion",
+        },
+        avatar_url: task.url.starts_with("https://github.com/")
+            .then(|| format!("https://github.com/{}.png?size=40", task.author)),
+        age_days: (now - task.created_at).num_days(),
+        last_activity_at: task.updated_at.unwrap_or(task.created_at),
+    }
+}
+
+fn project_contexts(projects: &[Project], now: DateTime) -> Vec<ProjectContext<'_>> {
+    projects.iter()
+        .map(|project| ProjectContext {
+            node_id: project.node_id.as_str(),
+            owner: project.owner.as_str(),
+            name: project.name.as_str(),
+            full_name: format!("{}/{}", project.owner, project.name),
+            url: project.url.as_str(),
+            tasks: project.tasks.iter().map(|task| task_context(task, now)).collect(),
+        })
+        .collect()
+}
+
+/// Formats a timestamp, by default like the timestamps in the plain text body always have been.
+fn datetime(value: &str, format: Option<&str>) -> Result<String, minijinja::Error> {
+    let datetime = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|err| minijinja::Error::new(ErrorKind::InvalidOperation, format!("invalid timestamp {}: {}", value, err)))?;
+    Ok(datetime.to_utc().format(format.unwrap_or("%Y-%m-%d %H:%M:%S UTC")).to_string())
+}
+
+fn environment(subject: Option<String>, text: Option<String>, html: Option<String>) -> Result<Environment<'static>, Box<dyn Error>> {
+    let mut environment = Environment::new();
+    environment.set_syntax(SyntaxConfig::builder().trim_blocks(true).lstrip_blocks(true).build()?);
+    environment.add_filter("datetime", datetime);
+    // the names decide about escaping
+    environment.add_template_owned("subject.txt", subject.unwrap_or(SUBJECT.to_string()))?;
+    environment.add_template_owned("body.txt", text.unwrap_or(TEXT.to_string()))?;
+    environment.add_template_owned("body.html", html.unwrap_or(HTML.to_string()))?;
+    Ok(environment)
+}
+
+fn sample_task(task_type: TaskType, id: i64, now: DateTime) -> Task {
+    let created_at = now - chrono::Duration::days(id);
+    Task {
+        node_id: format!("sample-{}", id),
+        observed_at: created_at,
+        task_type,
+        id,
+        title: format!("Sample task {}", id),
+        created_at,
+        url: format!("https://example.com/owner/repository/issues/{}", id),
+        author: "someone".to_string(),
+        updated_at: Some(now),
+        history: NotificationHistory { first_notified_at: Some(created_at), last_reminded_at: Some(now), reminder_count: 1, baselined: false },
+        missing_since: None,
+    }
+}
+
+fn sample_project(tasks: Vec<Task>) -> Project {
+    Project {
+        node_id: "sample".to_string(),
+        name: "repository".to_string(),
+        owner: "owner".to_string(),
+        url: "https://example.com/owner/repository".to_string(),
+        tasks,
+        activity_watermark: None,
+        first_seen_at: None,
+    }
+}
+
+impl Templates {
+    /// Uses the built-in default for every template that is not given. Fails unless the templates render a sample
+    /// notification, so mistakes show at startup instead of with the first notification.
+    pub fn new(subject: Option<String>, text: Option<String>, html: Option<String>) -> Result<Self, Box<dyn Error>> {
+        let overridden = subject.is_some() || text.is_some() || html.is_some();
+        let templates = Templates {
+            environment: environment(subject, text, html)?,
+            fallback: match overridden {
+                true => Some(environment(None, None, None)?),
+                false => None,
+            },
+        };
+
+        let now = chrono::Utc::now();
+        let notify = [sample_project(vec![sample_task(TaskType::Issue, 1, now), sample_task(TaskType::Pr, 2, now), sample_task(TaskType::Discussion, 3, now)])];
+        let remind = [sample_project(vec![sample_task(TaskType::Issue, 30, now)])];
+        let renamed = [ProjectRename { previous: "owner/previous".to_string(), current: "owner/repository".to_string(), url: "https://example.com/owner/repository".to_string() }];
+        let report = IdentityReport { identity: Some("sample"), notify: &notify, remind: &remind, renamed: &renamed };
+        let cycle = Cycle { started_at: now, source: "poll", build: None };
+        Self::render_with(&templates.environment, &[&report], &cycle)?;
+        Ok(templates)
+    }
+
+    /// Renders the notification, with the built-in templates if the configured ones fail.
+    pub fn render(&self, reports: &[&IdentityReport], cycle: &Cycle) -> Result<Notification, Box<dyn Error>> {
+        match (Self::render_with(&self.environment, reports, cycle), &self.fallback) {
+            (Err(err), Some(fallback)) => {
+                eprintln!("Failed to render the notification templates, using the built-in ones: {}", err);
+                Self::render_with(fallback, reports, cycle)
+            },
+            (result, _) => result,
+        }
+    }
+
+    fn render_with(environment: &Environment, reports: &[&IdentityReport], cycle: &Cycle) -> Result<Notification, Box<dyn Error>> {
+        let now = cycle.started_at;
+        let mut counts = Counts::default();
+        let reports: Vec<ReportContext> = reports.iter()
+            .map(|report| {
+                let report_counts = Counts {
+                    notify: TaskCounts::count(report.notify),
+                    remind: TaskCounts::count(report.remind),
+                    renamed: report.renamed.len(),
+                };
+                counts.notify.add(&report_counts.notify);
+                counts.remind.add(&report_counts.remind);
+                counts.renamed += report_counts.renamed;
+                ReportContext {
+                    identity: report.identity,
+                    notify: project_contexts(report.notify, now),
+                    remind: project_contexts(report.remind, now),
+                    renamed: report.renamed,
+                    counts: report_counts,
+                }
+            })
+            .collect();
+        let identity = match reports.as_slice() {
+            [report] => report.identity,
+            _ => None,
+        };
+        let context = Value::from(Serde(NotificationContext { reports, identity, counts, cycle }));
+
+        let render = |name: &str| environment.get_template(name)?.render(context.clone());
+        Ok(Notification {
+            // the subject has to fit on a single line
+            subject: render("subject.txt")?.split_whitespace().collect::<Vec<&str>>().join(" "),
+            text: render("body.txt")?.trim().to_string(),
+            html: render("body.html")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(notify: &[Project], remind: &[Project], renamed: &[ProjectRename]) -> Notification {
+        let report = IdentityReport { identity: None, notify, remind, renamed };
+        let cycle = Cycle { started_at: chrono::Utc::now(), source: "poll", build: None };
+        Templates::new(None, None, None).unwrap().render(&[&report], &cycle).unwrap()
+    }
+
+    #[test]
+    fn subject_and_opening_follow_the_content() {
+        let now = chrono::Utc::now();
+        let projects = [sample_project(vec![sample_task(TaskType::Issue, 1, now)])];
+        let renamed = [ProjectRename { previous: "owner/old".to_string(), current: "owner/new".to_string(), url: "https://example.com/owner/new".to_string() }];
+
+        let new = render(&projects, &projects, &renamed);
+        assert_eq!(new.subject, "New Unsubscribed Tasks");
+        assert!(new.text.starts_with("Tasks have been found"));
+
+        let reminder = render(&[], &projects, &renamed);
+        assert_eq!(reminder.subject, "Tasks Still Waiting for a Response");
+        assert!(reminder.text.starts_with("Tasks in your projects are still waiting"));
+        assert!(reminder.html.contains("still waiting for a response"));
+
+        let rename = render(&[], &[], &renamed);
+        assert_eq!(rename.subject, "Renamed Projects");
+        assert!(rename.text.starts_with("Projects you are monitoring have been renamed"));
+    }
+}
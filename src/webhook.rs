@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use ring::hmac;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::UnboundedSender;
+
+// GitHub caps webhook payloads at 25 MB
+const MAX_PAYLOAD_SIZE: usize = 25 * 1024 * 1024;
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+pub struct WebhookConfig {
+    pub listen: SocketAddr,
+    pub secret: String,
+}
+
+#[derive(Deserialize)]
+struct NodeReference {
+    node_id: String,
+}
+
+#[derive(Deserialize)]
+struct IssuesEvent {
+    action: String,
+    issue: NodeReference,
+}
+
+#[derive(Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    pull_request: NodeReference,
+}
+
+#[derive(Deserialize)]
+struct DiscussionEvent {
+    action: String,
+    discussion: NodeReference,
+}
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    // from_str_radix would accept a sign as well
+    if !input.len().is_multiple_of(2) || !input.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(input.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn verify_signature(key: &hmac::Key, signature: Option<&str>, payload: &[u8]) -> bool {
+    let Some(signature) = signature.and_then(|s| s.strip_prefix(SIGNATURE_PREFIX)) else {
+        return false;
+    };
+    match decode_hex(signature) {
+        Some(tag) => hmac::verify(key, payload, &tag).is_ok(),
+        None => false,
+    }
+}
+
+/// Extracts the node ID of the task an event refers to, if the event might have produced a new task.
+fn task_node_id(event: &str, payload: &[u8]) -> Result<Option<String>, serde_json::Error> {
+    let node_id = match event {
+        "issues" => {
+            let event: IssuesEvent = serde_json::from_slice(payload)?;
+            matches!(event.action.as_str(), "opened" | "reopened").then_some(event.issue.node_id)
+        },
+        "pull_request" => {
+            let event: PullRequestEvent = serde_json::from_slice(payload)?;
+            matches!(event.action.as_str(), "opened" | "reopened").then_some(event.pull_request.node_id)
+        },
+        "discussion" => {
+            let event: DiscussionEvent = serde_json::from_slice(payload)?;
+            matches!(event.action.as_str(), "created" | "reopened").then_some(event.discussion.node_id)
+        },
+        _ => None,
+    };
+    Ok(node_id)
+}
+
+fn respond(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(message.to_string())));
+    *response.status_mut() = status;
+    response
+}
+
+async fn handle_delivery(request: Request<Incoming>, key: &hmac::Key, sender: &UnboundedSender<Vec<String>>) -> Response<Full<Bytes>> {
+    if request.method() != Method::POST {
+        return respond(StatusCode::METHOD_NOT_ALLOWED, "only POST is supported");
+    }
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+    let event = header("X-GitHub-Event").unwrap_or_default();
+    let signature = header("X-Hub-Signature-256");
+
+    let payload = match Limited::new(request.into_body(), MAX_PAYLOAD_SIZE).collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(err) => {
+            eprintln!("Failed to read webhook payload: {}", err);
+            return respond(StatusCode::BAD_REQUEST, "unreadable payload");
+        },
+    };
+
+    if !verify_signature(key, signature.as_deref(), &payload) {
+        eprintln!("Rejected webhook delivery with invalid signature");
+        return respond(StatusCode::UNAUTHORIZED, "invalid signature");
+    }
+
+    match task_node_id(event.as_str(), &payload) {
+        Ok(Some(node_id)) => {
+            println!("Received {} webhook for {}", event, node_id);
+            if sender.send(vec![node_id]).is_err() {
+                return respond(StatusCode::SERVICE_UNAVAILABLE, "not accepting deliveries");
+            }
+            respond(StatusCode::ACCEPTED, "accepted")
+        },
+        Ok(None) => respond(StatusCode::OK, "ignored"),
+        Err(err) => {
+            eprintln!("Failed to parse {} webhook payload: {}", event, err);
+            respond(StatusCode::BAD_REQUEST, "invalid payload")
+        },
+    }
+}
+
+/// Accepts GitHub webhook deliveries and forwards the node IDs of potentially new tasks to the given sender.
+pub async fn serve(config: WebhookConfig, sender: UnboundedSender<Vec<String>>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let listener = TcpListener::bind(config.listen).await?;
+    let key = Arc::new(hmac::Key::new(hmac::HMAC_SHA256, config.secret.as_bytes()));
+    println!("Listening for webhook deliveries on {}", config.listen);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let key = key.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|request| {
+                let key = key.clone();
+                let sender = sender.clone();
+                async move { Ok::<_, hyper::Error>(handle_delivery(request, &key, &sender).await) }
+            });
+            if let Err(err) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                eprintln!("Failed to serve webhook connection: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"It's a Secret to Everybody";
+    const PAYLOAD: &[u8] = b"Hello, World!";
+    // the example from GitHub's documentation on validating webhook deliveries
+    const SIGNATURE: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn key() -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, SECRET)
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex("00ff7fA0"), Some(vec![0x00, 0xff, 0x7f, 0xa0]));
+        assert_eq!(decode_hex(""), Some(Vec::new()));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+f"), None);
+        assert_eq!(decode_hex("éé"), None);
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        assert!(verify_signature(&key(), Some(SIGNATURE), PAYLOAD));
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let wrong_key = hmac::Key::new(hmac::HMAC_SHA256, b"another secret");
+        assert!(!verify_signature(&wrong_key, Some(SIGNATURE), PAYLOAD));
+        assert!(!verify_signature(&key(), Some(SIGNATURE), b"Hello, World?"));
+        assert!(!verify_signature(&key(), Some(SIGNATURE.strip_prefix(SIGNATURE_PREFIX).unwrap()), PAYLOAD));
+        assert!(!verify_signature(&key(), Some(&SIGNATURE.replace("sha256=", "sha1=")), PAYLOAD));
+        assert!(!verify_signature(&key(), Some(&SIGNATURE[..SIGNATURE.len() - 1]), PAYLOAD));
+        assert!(!verify_signature(&key(), Some(&SIGNATURE.replace('e', "g")), PAYLOAD));
+        assert!(!verify_signature(&key(), Some("sha256="), PAYLOAD));
+        assert!(!verify_signature(&key(), None, PAYLOAD));
+    }
+
+    fn node_id(event: &str, field: &str, action: &str) -> Option<String> {
+        let payload = format!(r#"{{"action":"{}","{}":{{"node_id":"N1"}}}}"#, action, field);
+        task_node_id(event, payload.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn extracts_node_ids_of_new_tasks() {
+        let cases = [
+            ("issues", "issue", "opened", true),
+            ("issues", "issue", "reopened", true),
+            ("issues", "issue", "closed", false),
+            ("issues", "issue", "edited", false),
+            ("pull_request", "pull_request", "opened", true),
+            ("pull_request", "pull_request", "reopened", true),
+            ("pull_request", "pull_request", "closed", false),
+            ("pull_request", "pull_request", "synchronize", false),
+            ("discussion", "discussion", "created", true),
+            ("discussion", "discussion", "reopened", true),
+            ("discussion", "discussion", "opened", false),
+            ("discussion", "discussion", "answered", false),
+            ("issue_comment", "issue", "created", false),
+            ("ping", "hook", "opened", false),
+        ];
+        for (event, field, action, expected) in cases {
+            assert_eq!(node_id(event, field, action).is_some(), expected, "{} {}", event, action);
+        }
+        assert_eq!(node_id("issues", "issue", "opened").as_deref(), Some("N1"));
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        assert!(task_node_id("issues", b"{\"action\":\"opened\"}").is_err());
+        assert!(task_node_id("pull_request", b"not json").is_err());
+        assert_eq!(task_node_id("ping", b"not json").unwrap(), None);
+    }
+}